@@ -0,0 +1,56 @@
+use futures::StreamExt;
+use nndi::{record, scan, Recorder, Scan, Sink};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+pub extern crate ffmpeg_next as ffmpeg;
+
+#[tokio::main]
+pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ffmpeg_next::init()?;
+
+    // Set-up the log and traces handler
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(EnvFilter::from_default_env())
+        .init();
+
+    let mut scan = Scan::new(Default::default())?;
+
+    let source = {
+        let mut events = std::pin::pin!(scan.events());
+
+        loop {
+            match events.next().await {
+                Some(scan::Event::Added(source)) => break source,
+                Some(_) => continue,
+                None => return Err("The mDNS discovery stopped unexpectedly".into()),
+            }
+        }
+    };
+
+    let sink = Sink::new(
+        &source,
+        nndi::sink::Config {
+            video_queue: 16,
+            audio_queue: 16,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tracing::info!("Recording source: {source:?}");
+
+    let recorder = Recorder::start(
+        sink,
+        record::Config {
+            path: "recording.mkv".into(),
+            max_duration: Some(std::time::Duration::from_secs(60)),
+            ..Default::default()
+        },
+    );
+
+    // Record until the source disconnects
+    recorder.finished().await?;
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
-use nndi::{Scan, Sink};
+use futures::StreamExt;
+use nndi::{scan, Scan, Sink};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 pub extern crate ffmpeg_next as ffmpeg;
@@ -13,18 +14,18 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with(EnvFilter::from_default_env())
         .init();
 
-    let mut scan = Scan::new()?;
+    let mut scan = Scan::new(Default::default())?;
 
-    let source = loop {
-        let sources = scan.sources().collect::<Vec<_>>();
+    let source = {
+        let mut events = std::pin::pin!(scan.events());
 
-        std::thread::sleep(std::time::Duration::from_millis(100));
-
-        let Some(source) = sources.get(0) else {
-            continue;
-        };
-
-        break (*source).clone();
+        loop {
+            match events.next().await {
+                Some(scan::Event::Added(source)) => break source,
+                Some(_) => continue,
+                None => return Err("The mDNS discovery stopped unexpectedly".into()),
+            }
+        }
     };
 
     let sink = Sink::new(
@@ -32,6 +33,7 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
         nndi::sink::Config {
             video_queue: 4,
             audio_queue: 4,
+            reconnect: Some(Default::default()),
             ..Default::default()
         },
     )
@@ -39,11 +41,25 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tracing::info!("Connected to source: {source:?}");
 
-    let t0 = std::thread::spawn({
+    tokio::spawn({
         let sink = sink.clone();
 
-        move || {
-            for (idx, video) in sink.video_frames().enumerate() {
+        async move {
+            let mut events = std::pin::pin!(sink.events());
+
+            while let Some(event) = events.next().await {
+                tracing::info!("Connection event: {event:?}");
+            }
+        }
+    });
+
+    let video = tokio::spawn({
+        let sink = sink.clone();
+
+        async move {
+            let mut frames = std::pin::pin!(sink.video_stream().enumerate());
+
+            while let Some((idx, video)) = frames.next().await {
                 let video = video.expect("Unable to decode `video` frame");
 
                 tracing::warn!(
@@ -62,8 +78,10 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    let t1 = std::thread::spawn(move || {
-        for (idx, audio) in sink.audio_frames().enumerate() {
+    let audio = tokio::spawn(async move {
+        let mut frames = std::pin::pin!(sink.audio_stream().enumerate());
+
+        while let Some((idx, audio)) = frames.next().await {
             let audio = audio.expect("Unable to decode `audio` frame");
 
             tracing::warn!(
@@ -75,8 +93,8 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    t0.join().expect("Unnable to join `video` thread");
-    t1.join().expect("Unnable to join `audio` thread");
+    video.await?;
+    audio.await?;
 
     Ok(())
 }
@@ -1,4 +1,6 @@
-use nndi::{ffmpeg, Source};
+use std::sync::Arc;
+
+use nndi::{playout, Playout, Source};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 #[tokio::main]
@@ -11,35 +13,33 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with(EnvFilter::from_default_env())
         .init();
 
-    let source = Source::new(nndi::source::Config {
-        name: "super source".into(),
-        ..Default::default()
-    })
-    .await?;
-
-    let timebase = ffmpeg::Rational::new(1, 30);
-    let mut frame = ffmpeg::frame::Video::new(ffmpeg::format::Pixel::RGB24, 1920, 1080);
-
-    let mut idx = 0u8;
+    let path = std::env::args().nth(1).ok_or("Usage: send <media file>")?;
 
-    loop {
-        std::thread::sleep(std::time::Duration::from_millis(
-            (1000 * timebase.numerator() / timebase.denominator()) as u64,
-        ));
+    let source = Arc::new(
+        Source::new(nndi::source::Config {
+            name: "super source".into(),
+            ..Default::default()
+        })
+        .await?,
+    );
 
-        for pix in frame.plane_mut(0) {
-            *pix = (idx, idx.wrapping_mul(2), idx.wrapping_mul(3));
-
-            idx = idx.wrapping_add(1);
+    let mut tally = source.subscribe_tally();
+    tokio::spawn(async move {
+        while tally.changed().await.is_ok() {
+            tracing::info!("Tally changed: {:?}", *tally.borrow_and_update());
         }
-        source.broadcast_video(&frame, timebase).await?;
+    });
+
+    let playout = Playout::start(
+        source.clone(),
+        playout::Config {
+            path: path.into(),
+            looping: true,
+            passthrough: true,
+        },
+    )?;
 
-        idx = idx.wrapping_add(1);
+    playout.finished().await?;
 
-        tracing::info!(
-            "Currently connected peers: {} (tally: {:?})",
-            source.peers().await.len(),
-            source.tally().await
-        );
-    }
+    Ok(())
 }
@@ -0,0 +1,113 @@
+use std::{collections::HashSet, net::SocketAddr, time::Duration};
+
+use tokio::net::TcpStream;
+
+use crate::{
+    io::{Reader, Stream, Writer},
+    scan::{Discovered, Origin, SourceInfo},
+    Result,
+};
+
+use super::message::{Announcement, Message, Subscription};
+
+/// The delay before connecting to the server again, after the connection was lost.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Connect to the `server`, and send it the initial `message`.
+async fn connect(server: SocketAddr, message: Message) -> Result<(Reader, Writer)> {
+    let (reader, mut writer) = Stream::from(TcpStream::connect(server).await?).into_split();
+    message.send(&mut writer).await?;
+
+    Ok((reader, writer))
+}
+
+/// Keep the `source` registered to the discovery `server`, reconnecting when the connection is lost.
+pub async fn register(server: SocketAddr, source: SourceInfo) {
+    loop {
+        if let Err(err) = registration(server, &source).await {
+            tracing::warn!(
+                "Registration of `{source}` to the discovery server `{server}` lost: {err}"
+            );
+        }
+
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn registration(server: SocketAddr, source: &SourceInfo) -> Result {
+    // The writing half is kept around, since dropping it would shut the connection down
+    let (mut reader, _writer) =
+        connect(server, Message::Register(Announcement::from(source))).await?;
+
+    tracing::debug!("Registered `{source}` to the discovery server `{server}`");
+
+    // The source stays registered for as long as the connection is open
+    loop {
+        reader.recv().await?;
+    }
+}
+
+/// Forward the sources from the discovery `server` watched in `groups` to the `discovered` channel,
+/// reconnecting when the connection is lost, until the channel is closed.
+pub async fn subscribe(
+    server: SocketAddr,
+    groups: Vec<String>,
+    discovered: flume::Sender<Discovered>,
+) {
+    while !discovered.is_disconnected() {
+        let mut known = HashSet::new();
+
+        if let Err(err) = subscription(server, &groups, &discovered, &mut known).await {
+            tracing::warn!("Subscription to the discovery server `{server}` lost: {err}");
+        }
+
+        // The sources known from the server cannot be considered alive anymore
+        for key in known {
+            discovered
+                .send(Discovered::Removed(Origin::Discovery, key))
+                .ok();
+        }
+
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn subscription(
+    server: SocketAddr,
+    groups: &[String],
+    discovered: &flume::Sender<Discovered>,
+    known: &mut HashSet<String>,
+) -> Result {
+    let subscription = Subscription {
+        groups: groups.join(","),
+    };
+    let (mut reader, _writer) = connect(server, Message::Subscribe(subscription)).await?;
+
+    tracing::debug!("Subscribed to the discovery server `{server}`");
+
+    loop {
+        let event = match Message::recv(&mut reader).await? {
+            Message::Added(announcement) => {
+                let source = SourceInfo::from(announcement);
+                known.insert(source.to_string());
+
+                Discovered::Resolved(Origin::Discovery, source)
+            }
+            Message::Removed(announcement) => {
+                let key = SourceInfo::from(announcement).to_string();
+                known.remove(&key);
+
+                Discovered::Removed(Origin::Discovery, key)
+            }
+            other => {
+                tracing::debug!("Ignored discovery message from `{server}`: {other:?}");
+
+                continue;
+            }
+        };
+
+        if discovered.send(event).is_err() {
+            break Ok(());
+        }
+    }
+}
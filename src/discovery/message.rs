@@ -0,0 +1,137 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    io::{
+        frame::{text, Frame},
+        Reader, Writer,
+    },
+    scan::SourceInfo,
+    Result,
+};
+
+/// A message exchanged with a discovery server, in the `nndi`-specific protocol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Message {
+    /// Register a source to the server, for as long as the connection is open.
+    #[serde(rename = "nndi_discovery_register")]
+    Register(Announcement),
+
+    /// Subscribe to the sources registered to the server.
+    #[serde(rename = "nndi_discovery_subscribe")]
+    Subscribe(Subscription),
+
+    /// A source was registered to the server.
+    #[serde(rename = "nndi_discovery_added")]
+    Added(Announcement),
+
+    /// A source was unregistered from the server.
+    #[serde(rename = "nndi_discovery_removed")]
+    Removed(Announcement),
+}
+
+impl Message {
+    fn from_block(block: &text::Block) -> Result<Self> {
+        let mut text = std::io::Cursor::new(&block.data.0);
+
+        Ok(quick_xml::de::from_reader::<_, Self>(&mut text)?)
+    }
+
+    fn to_block(&self) -> text::Block {
+        let text = quick_xml::se::to_string(&self)
+            .expect("Unable to serialize XML structure, should not be the case");
+
+        text::Block::data(text)
+    }
+
+    /// Retrieve the next [`Message`] from the `reader`, discarding anything else.
+    pub async fn recv(reader: &mut Reader) -> Result<Self> {
+        loop {
+            if let Frame::Text(block) = reader.recv().await? {
+                match Self::from_block(&block) {
+                    Ok(message) => break Ok(message),
+                    Err(_) => tracing::warn!(
+                        "Unhandled discovery message received from `{}`: {}",
+                        reader.peer_addr()?,
+                        block.data
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Send the [`Message`] over the `writer`.
+    pub async fn send(&self, writer: &mut Writer) -> Result {
+        writer.send(&Frame::Text(self.to_block())).await
+    }
+}
+
+/// The description of a source registered to a discovery server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Announcement {
+    /// The name of the machine hosting the source.
+    #[serde(rename = "@machine")]
+    pub machine: String,
+
+    /// The name of the source on it's machine.
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    /// The comma-separated groups of the source.
+    #[serde(rename = "@groups", default)]
+    pub groups: String,
+
+    /// The comma-separated addresses of the source,
+    /// left empty to use the address the source registered from.
+    #[serde(rename = "@addresses", default)]
+    pub addresses: String,
+
+    /// The port the source listens on.
+    #[serde(rename = "@port")]
+    pub port: u16,
+}
+
+impl From<&SourceInfo> for Announcement {
+    fn from(source: &SourceInfo) -> Self {
+        Self {
+            machine: source.machine.clone(),
+            name: source.name.clone(),
+            groups: source.groups.join(","),
+            addresses: source
+                .addresses
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+            port: source.port,
+        }
+    }
+}
+
+impl From<Announcement> for SourceInfo {
+    fn from(announcement: Announcement) -> Self {
+        let mut addresses: Vec<_> = announcement
+            .addresses
+            .split(',')
+            .filter_map(|addr| addr.trim().parse::<IpAddr>().ok())
+            .collect();
+        addresses.sort();
+
+        Self {
+            machine: announcement.machine,
+            name: announcement.name,
+            groups: SourceInfo::parse_groups(&announcement.groups),
+            addresses,
+            port: announcement.port,
+        }
+    }
+}
+
+/// A subscription to the sources registered to a discovery server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    /// The comma-separated groups to watch, left empty to watch all of them.
+    #[serde(rename = "@groups", default)]
+    pub groups: String,
+}
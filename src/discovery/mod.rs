@@ -0,0 +1,20 @@
+//! Everything related to `nndi` _discovery servers_, a centralised directory of sources
+//! used where mDNS cannot reach, such as across routed networks.
+//!
+//! Sources register to the server with [`crate::source::Config::discovery`], and
+//! receivers query it with [`crate::scan::Config::discovery`], instead of using mDNS.
+//!
+//! The protocol is specific to this crate and is not the one of the _NDI Discovery Server_,
+//! so the [`Server`] only serves `nndi` sources and receivers, and these cannot register
+//! to nor query an _NDI Discovery Server_.
+
+mod message;
+
+mod server;
+pub use server::Server;
+
+pub(crate) mod client;
+
+/// The default port of discovery servers, the same as the _NDI Discovery Server_ one,
+/// so both cannot run on the same host with their defaults.
+pub const DEFAULT_PORT: u16 = 5959;
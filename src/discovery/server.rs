@@ -0,0 +1,209 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use slab::Slab;
+use tokio::{
+    net::{TcpListener, ToSocketAddrs},
+    sync::RwLock,
+    task::JoinHandle,
+};
+
+use crate::{
+    io::{Reader, Stream, Writer},
+    scan::SourceInfo,
+    Result,
+};
+
+use super::message::{Announcement, Message, Subscription};
+
+/// A client subscribed to the sources of the [`Server`].
+struct Subscriber {
+    groups: Vec<String>,
+    sender: flume::Sender<Message>,
+}
+
+/// The sources registered to the [`Server`], and the clients subscribed to them.
+#[derive(Default)]
+struct Registry {
+    sources: Slab<SourceInfo>,
+    subscribers: Slab<Subscriber>,
+}
+
+impl Registry {
+    /// Send the `message` about the `source` to all the subscribers watching one of it's groups.
+    fn publish(&self, message: Message, source: &SourceInfo) {
+        for (_, subscriber) in &self.subscribers {
+            if subscriber.groups.is_empty() || source.shares_any(&subscriber.groups) {
+                subscriber.sender.send(message.clone()).ok();
+            }
+        }
+    }
+}
+
+type Lock<T> = Arc<RwLock<T>>;
+
+/// An `nndi` _discovery server_, keeping a directory of the sources registered to it,
+/// and notifying the subscribed receivers of their changes.
+///
+/// The server stops when dropped, disconnecting all of it's clients.
+pub struct Server {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl Server {
+    /// Start a new [`Server`] listening on `addr`, usually on the [`super::DEFAULT_PORT`].
+    pub async fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+
+        tracing::debug!("Discovery server listening on `{local_addr}`");
+
+        Ok(Self {
+            local_addr,
+            task: tokio::spawn(Self::listen(listener)),
+        })
+    }
+
+    /// The local address the [`Server`] listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    async fn listen(listener: TcpListener) {
+        let registry = <Lock<Registry>>::default();
+
+        // Clients are handled within the listener task, so they are all dropped along with it
+        let mut clients = FuturesUnordered::new();
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, addr) = match accepted {
+                        Ok(accepted) => accepted,
+                        Err(err) => {
+                            tracing::warn!("Unable to accept an incoming connection: {err}");
+
+                            continue;
+                        }
+                    };
+
+                    let registry = registry.clone();
+                    clients.push(async move {
+                        if let Err(err) = Self::handle(stream.into(), addr, registry).await {
+                            tracing::debug!("Discovery client `{addr}` disconnected: {err}");
+                        }
+                    });
+                }
+
+                Some(()) = clients.next() => (),
+            }
+        }
+    }
+
+    async fn handle(stream: Stream, addr: SocketAddr, registry: Lock<Registry>) -> Result {
+        let (mut reader, writer) = stream.into_split();
+
+        match Message::recv(&mut reader).await? {
+            Message::Register(announcement) => {
+                Self::register(reader, writer, addr, announcement, registry).await
+            }
+            Message::Subscribe(subscription) => {
+                Self::subscribe(reader, writer, subscription, registry).await
+            }
+            other => {
+                tracing::warn!("Unexpected discovery message from `{addr}`: {other:?}");
+
+                Ok(())
+            }
+        }
+    }
+
+    async fn register(
+        mut reader: Reader,
+        // Kept around, since dropping it would shut the connection down
+        _writer: Writer,
+        addr: SocketAddr,
+        announcement: Announcement,
+        registry: Lock<Registry>,
+    ) -> Result {
+        let mut source = SourceInfo::from(announcement);
+        if source.addresses.is_empty() {
+            source.addresses = vec![addr.ip().to_canonical()];
+        }
+
+        tracing::debug!("Source `{source}` registered from `{addr}`");
+
+        let key = {
+            let mut registry = registry.write().await;
+            registry.publish(Message::Added((&source).into()), &source);
+
+            registry.sources.insert(source)
+        };
+
+        // The source stays registered for as long as it's connection is open
+        let res = Self::drain(&mut reader).await;
+
+        let mut registry = registry.write().await;
+        if let Some(source) = registry.sources.try_remove(key) {
+            tracing::debug!("Source `{source}` unregistered from `{addr}`");
+
+            registry.publish(Message::Removed((&source).into()), &source);
+        }
+
+        res
+    }
+
+    async fn subscribe(
+        mut reader: Reader,
+        mut writer: Writer,
+        subscription: Subscription,
+        registry: Lock<Registry>,
+    ) -> Result {
+        let groups = SourceInfo::parse_groups(&subscription.groups);
+        let (sender, receiver) = flume::unbounded();
+
+        // Queue the currently registered sources before subscribing, so no change is missed
+        let key = {
+            let mut registry = registry.write().await;
+            for (_, source) in &registry.sources {
+                if groups.is_empty() || source.shares_any(&groups) {
+                    sender.send(Message::Added(source.into())).ok();
+                }
+            }
+
+            registry.subscribers.insert(Subscriber { groups, sender })
+        };
+
+        let res = tokio::try_join!(
+            Self::drain(&mut reader),
+            Self::forward(&mut writer, &receiver)
+        );
+
+        registry.write().await.subscribers.try_remove(key);
+
+        res.map(|_: ((), ())| ())
+    }
+
+    /// Wait for the client to disconnect, discarding anything it sends.
+    async fn drain(reader: &mut Reader) -> Result {
+        loop {
+            reader.recv().await?;
+        }
+    }
+
+    /// Forward the queued messages to the client, until the queue is closed.
+    async fn forward(writer: &mut Writer, receiver: &flume::Receiver<Message>) -> Result {
+        while let Ok(message) = receiver.recv_async().await {
+            message.send(writer).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -24,14 +24,40 @@ pub enum Error {
     #[error(transparent)]
     Codec(#[from] ffmpeg::Error),
 
+    /// Background task error.
+    #[error(transparent)]
+    Task(#[from] tokio::task::JoinError),
+
     /// The channel was closed.
     #[error("The channel was closed, and cannot accept data anymore")]
     ClosedChannel,
 
+    /// The peer could not keep up with frames which are never dropped.
+    #[error("The peer could not keep up with the audio or metadata frames sent to it")]
+    Overflow,
+
     /// The peer timed out.
     #[error("The peer timed out while awaiting mandatory data")]
     Timeout(#[from] tokio::time::error::Elapsed),
 
+    /// The source address could not be parsed.
+    #[error("Invalid source address `{0}`, expected `ndi://host:port/name`")]
+    InvalidAddress(String),
+
+    /// The source reached at an address is not the one it designates.
+    #[error("Expected the source `{expected}` at this address, but reached `{found}`")]
+    UnexpectedSource {
+        /// The name of the source designated by the address.
+        expected: String,
+
+        /// The name of the source which answered.
+        found: String,
+    },
+
+    /// None of the configured network interfaces had a usable address.
+    #[error("No usable address found on the network interface(s) `{0}`")]
+    NoInterface(String),
+
     /// The packet was unknown, or unsupported.
     #[error("Unknown frame kind from packet header")]
     UnknownKind,
@@ -1,30 +1,50 @@
 use binrw::{BinRead, BinWrite};
-use ffmpeg::codec;
+use ffmpeg::format::{sample, Sample};
 use strum::AsRefStr;
 
 pub type Block = super::Block<Spec, super::BytesEof>;
 
-#[derive(Debug, PartialEq, BinRead, BinWrite)]
+/// The header of an audio block, describing the samples it carries.
+#[derive(Debug, Clone, PartialEq, BinRead, BinWrite)]
 #[brw(little)]
 pub struct Spec {
+    /// The format of the samples.
     pub fourcc: FourCCAudioType,
+
+    /// The number of samples per channel.
     pub samples: u32,
+
+    /// The number of channels.
     pub num_channels: u32,
+
+    /// The sample rate, in Hz.
     pub sample_rate: u32,
 }
 
+/// The format of the samples carried in an audio block.
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, PartialEq, AsRefStr, BinRead, BinWrite)]
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr, BinRead, BinWrite)]
 #[strum(serialize_all = "lowercase")]
 pub enum FourCCAudioType {
+    /// Planar 32-bit floats, one channel after the other.
     #[brw(magic = b"fowt")]
     FOWT,
 
+    /// Packed 16-bit little-endian integers.
     #[brw(magic = b"sowt")]
     SOWT,
 }
 
+/// The length of each plane of the `frame` on the wire, in bytes, for `channels` channels.
+///
+/// Planes are laid out one after the other on the wire, each one of them holding
+/// all the samples of a single channel when the format is planar.
+pub fn plane_len(frame: &ffmpeg::frame::Audio, channels: usize) -> usize {
+    frame.samples() * frame.format().bytes() * if frame.is_packed() { channels } else { 1 }
+}
+
 impl FourCCAudioType {
+    /// The `FourCC` as a little-endian integer, as found in `codec_tag`s.
     pub fn to_code(&self) -> u32 {
         let bytes = self
             .as_ref()
@@ -35,10 +55,19 @@ impl FourCCAudioType {
         u32::from_le_bytes(bytes)
     }
 
-    pub fn to_codec(&self) -> Option<ffmpeg::Codec> {
-        codec::decoder::find(match self {
-            FourCCAudioType::FOWT => codec::Id::PCM_S16LE,
-            FourCCAudioType::SOWT => codec::Id::PCM_S16LE,
-        })
+    /// Select the wire format best suited to carry samples of the provided `format`.
+    pub fn from_format(format: Sample) -> Self {
+        match format {
+            Sample::I16(_) => FourCCAudioType::SOWT,
+            _ => FourCCAudioType::FOWT,
+        }
+    }
+
+    /// The sample format of the data carried on the wire.
+    pub fn to_format(&self) -> Sample {
+        match self {
+            FourCCAudioType::FOWT => Sample::F32(sample::Type::Planar),
+            FourCCAudioType::SOWT => Sample::I16(sample::Type::Packed),
+        }
     }
 }
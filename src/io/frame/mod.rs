@@ -55,6 +55,13 @@ impl Frame {
         })
     }
 
+    /// Create a _text_ frame from arbitrary `xml`, ensuring it is well-formed.
+    pub fn metadata(xml: &str) -> Result<Self> {
+        text::validate(xml)?;
+
+        Ok(Self::Text(text::Block::data(xml.to_string())))
+    }
+
     pub fn version() -> Self {
         Self::Text(
             text::Metadata::Version(text::Version {
@@ -81,6 +88,14 @@ impl Frame {
         Self::Text(text::Metadata::Video(text::Video { quality }).to_block())
     }
 
+    pub fn tally(tally: text::Tally) -> Self {
+        Self::Text(text::Metadata::Tally(tally).to_block())
+    }
+
+    pub fn tally_echo(tally: text::Tally) -> Self {
+        Self::Text(text::Metadata::TallyEcho(tally).to_block())
+    }
+
     pub fn enabled_streams(video: bool, audio: bool) -> Self {
         Self::Text(
             text::Metadata::EnabledStreams(text::EnabledStreams {
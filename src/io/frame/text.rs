@@ -7,38 +7,46 @@ use crate::Result;
 
 pub type Block = super::Block<[u8; 8], binrw::NullString>;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A _metadata_ entry known to the protocol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Metadata {
+    /// The software _version_ of the peer.
     #[serde(rename = "ndi_version")]
     Version(Version),
 
+    /// The _name_ of the peer.
     #[serde(rename = "ndi_identify")]
     Identify(Identify),
 
+    /// The _video_ parameters requested by the peer.
     #[serde(rename = "ndi_video")]
     Video(Video),
 
+    /// The _enabled streams_ of the peer.
     #[serde(rename = "ndi_enabled_streams")]
     EnabledStreams(EnabledStreams),
 
+    /// A _connection feedback_ from the peer.
     #[serde(rename = "ntk_conn_feedback")]
     ConnectionFeedback(ConnectionFeedback),
 
+    /// The _tally_ sent by the peer.
     #[serde(rename = "ndi_tally")]
     Tally(Tally),
 
+    /// The _tally_ echoed back by the peer.
     #[serde(rename = "ndi_tally_echo")]
     TallyEcho(Tally),
 }
 
 impl Metadata {
-    pub fn from_block(block: &Block) -> Result<Self> {
+    pub(crate) fn from_block(block: &Block) -> Result<Self> {
         let mut text = std::io::Cursor::new(&block.data.0);
 
         Ok(quick_xml::de::from_reader::<_, Self>(&mut text)?)
     }
 
-    pub fn to_block(&self) -> Block {
+    pub(crate) fn to_block(&self) -> Block {
         let text = quick_xml::se::to_string(&self)
             .expect("Unable to serialize XML structure, should not be the case");
 
@@ -46,6 +54,74 @@ impl Metadata {
     }
 }
 
+/// A _metadata_ message received from a peer.
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// A metadata entry known to the protocol.
+    Known(Metadata),
+
+    /// A metadata entry unknown to the protocol, as raw XML.
+    Unknown(String),
+}
+
+impl Message {
+    pub(crate) fn from_block(block: &Block) -> Self {
+        match Metadata::from_block(block) {
+            Ok(metadata) => Self::Known(metadata),
+            Err(_) => Self::Unknown(String::from_utf8_lossy(&block.data).into_owned()),
+        }
+    }
+}
+
+/// Ensure the provided `xml` is well-formed, with exactly one root element and no text around it,
+/// before it is sent on the wire, since peers would choke on it otherwise.
+pub(crate) fn validate(xml: &str) -> Result {
+    use quick_xml::{events::Event, DeError};
+
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let (mut depth, mut roots) = (0usize, 0usize);
+
+    loop {
+        let event = reader.read_event().map_err(DeError::from)?;
+
+        match &event {
+            Event::Start(element) | Event::Empty(element) => {
+                element
+                    .attributes()
+                    .try_for_each(|attr| attr.map(drop))
+                    .map_err(DeError::from)?;
+
+                if depth == 0 {
+                    roots += 1;
+                }
+                if roots > 1 {
+                    return Err(DeError::Custom("Multiple root elements".into()).into());
+                }
+            }
+            Event::Text(text) if depth == 0 && !text.iter().all(u8::is_ascii_whitespace) => {
+                return Err(DeError::Custom("Text outside of the root element".into()).into());
+            }
+            Event::CData(_) if depth == 0 => {
+                return Err(DeError::Custom("Text outside of the root element".into()).into());
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+
+    if depth != 0 || roots == 0 {
+        return Err(DeError::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
 /// Metadata definition for _version_ in the protocol.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Version {
@@ -87,7 +163,7 @@ pub struct Video {
 }
 
 /// Different video qualities available in the protocol.
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum VideoQuality {
     /// High definition video stream.
@@ -122,30 +198,42 @@ pub struct EnabledStreams {
     pub shq_short_dc: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Metadata definition for _connection feedback_ in the protocol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionFeedback {
+    /// The connection the feedback is about.
     pub connection: Connection,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A connection reported in the _connection feedback_.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Connection {
+    /// The name of the connected peer.
     #[serde(rename = "@name")]
     pub name: String,
+
+    /// The address of the connected peer.
     #[serde(rename = "@addr")]
     pub addr: SocketAddr,
+
+    /// The state of the connection.
     #[serde(rename = "@state")]
     pub state: ConnectionState,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Different connection states available in the protocol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ConnectionState {
+    /// The connection is established.
     Up,
+
+    /// The connection is lost.
     Down,
 }
 
 /// Metadata definition for _tally_ in the protocol.
-#[derive(Debug, Default, Clone, BitOr, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, BitOr, Serialize, Deserialize)]
 pub struct Tally {
     /// Whether we currently are _on program_.
     #[serde(rename = "@on_program")]
@@ -155,3 +243,26 @@ pub struct Tally {
     #[serde(rename = "@on_preview")]
     pub on_preview: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_accepts_well_formed_xml() {
+        assert!(validate(r#"<ptz_preset id="1"/>"#).is_ok());
+        assert!(validate(r#"<camera><zoom value="0.5"/></camera>"#).is_ok());
+        assert!(validate("\n  <camera>zoom</camera>\n").is_ok());
+    }
+
+    #[test]
+    fn it_rejects_malformed_xml() {
+        assert!(validate("").is_err());
+        assert!(validate("hello world !").is_err());
+        assert!(validate("<camera>").is_err());
+        assert!(validate("<camera></zoom>").is_err());
+        assert!(validate(r#"<camera zoom="0.5 />"#).is_err());
+        assert!(validate("hello <a/>").is_err());
+        assert!(validate("<a/><b/>").is_err());
+    }
+}
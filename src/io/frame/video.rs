@@ -1,41 +1,87 @@
 use binrw::{BinRead, BinWrite};
 use chrono::Utc;
+use ffmpeg::{codec, format::Pixel};
 use strum::AsRefStr;
 
+use crate::Result;
+
 pub type Block = super::Block<Spec, super::BytesEof>;
 
-#[derive(Debug, Default, PartialEq, BinRead, BinWrite)]
+/// The time base of the timestamps on the wire, in 100ns units.
+pub const TIMEBASE: ffmpeg::Rational = ffmpeg::Rational(1, 10_000_000);
+
+/// The header of a video block, describing the compressed frame it carries.
+#[derive(Debug, Default, Clone, PartialEq, BinRead, BinWrite)]
 #[brw(little)]
 pub struct Spec {
+    /// The format of the compressed frame.
     pub fourcc: FourCCVideoType,
+
+    /// The width of the frame, in pixels.
     pub width: u32,
+
+    /// The height of the frame, in pixels.
     pub height: u32,
+
+    /// The numerator of the framerate.
     pub fps_num: u32,
+
+    /// The denominator of the framerate.
     pub fps_den: u32,
+
+    /// The display aspect ratio of the frame, usually `width / height`.
     pub aspect_ratio: f32,
+
+    #[doc(hidden)]
     pub _1: [u8; 4],
+
+    /// Whether the frame is progressive, or interlaced.
     pub frame_format: FrameFormat,
+
+    #[doc(hidden)]
     pub _2: [u8; 4],
+
+    #[doc(hidden)]
     pub _3: [u8; 4],
+
+    #[doc(hidden)]
     pub _4: [u8; 4],
+
+    #[doc(hidden)]
     pub _5: [u8; 4],
+
+    /// The time at which the frame was produced.
     pub timestamp: Timestamp,
+
+    /// The per-frame metadata, as `xml`, possibly empty.
     pub metadata: binrw::NullString,
 }
 
+/// The format of the compressed frame carried in a video block.
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, Default, PartialEq, AsRefStr, BinRead, BinWrite)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, AsRefStr, BinRead, BinWrite)]
 #[strum(serialize_all = "UPPERCASE")]
 pub enum FourCCVideoType {
+    /// SpeedHQ, in `4:2:2`.
     #[brw(magic = b"SHQ2")]
     #[default]
     SHQ2,
 
+    /// SpeedHQ, in `4:2:2:4` with an alpha channel.
     #[brw(magic = b"SHQ7")]
     SHQ7,
+
+    /// Long-GOP H.264, carried in a [`Packet`] only understood by `nndi`.
+    #[brw(magic = b"H264")]
+    H264,
+
+    /// Long-GOP HEVC, carried in a [`Packet`] only understood by `nndi`.
+    #[brw(magic = b"HEVC")]
+    HEVC,
 }
 
 impl FourCCVideoType {
+    /// The `FourCC` as a little-endian integer, as found in `codec_tag`s.
     pub fn to_code(&self) -> u32 {
         let bytes = self
             .as_ref()
@@ -45,22 +91,151 @@ impl FourCCVideoType {
 
         u32::from_le_bytes(bytes)
     }
+
+    /// Select the wire format best suited to carry pixels of the provided `format`.
+    pub fn from_format(format: Pixel) -> Self {
+        let alpha = format.descriptor().is_some_and(|descriptor| {
+            // SAFETY: The descriptor pointer is checked for nullity in `Pixel::descriptor`,
+            // and points to a static table entry in `libavutil`.
+            let flags = unsafe { (*descriptor.as_ptr()).flags };
+
+            flags & ffmpeg::ffi::AV_PIX_FMT_FLAG_ALPHA as u64 != 0
+        });
+
+        if alpha {
+            FourCCVideoType::SHQ7
+        } else {
+            FourCCVideoType::SHQ2
+        }
+    }
+
+    /// The pixel format of the data carried on the wire.
+    pub fn to_format(&self) -> Pixel {
+        match self {
+            FourCCVideoType::SHQ2 => Pixel::YUV422P,
+            FourCCVideoType::SHQ7 => Pixel::YUVA422P,
+            FourCCVideoType::H264 | FourCCVideoType::HEVC => Pixel::YUV420P,
+        }
+    }
+
+    /// The codec of the data carried on the wire.
+    pub fn to_codec(&self) -> codec::Id {
+        match self {
+            FourCCVideoType::SHQ2 | FourCCVideoType::SHQ7 => codec::Id::SPEEDHQ,
+            FourCCVideoType::H264 => codec::Id::H264,
+            FourCCVideoType::HEVC => codec::Id::HEVC,
+        }
+    }
+
+    /// The width the frames of `width` pixels are coded at, as SpeedHQ slices
+    /// the frames in macroblocks of 16 pixels wide.
+    pub fn coded_width(&self, width: u32) -> u32 {
+        match self {
+            FourCCVideoType::SHQ2 | FourCCVideoType::SHQ7 => width.next_multiple_of(16),
+            FourCCVideoType::H264 | FourCCVideoType::HEVC => width,
+        }
+    }
+
+    /// Whether the blocks reference each other, and carry their data in a [`Packet`].
+    pub fn is_inter(&self) -> bool {
+        matches!(self, FourCCVideoType::H264 | FourCCVideoType::HEVC)
+    }
 }
 
+/// The data of a block of an inter-frame codec, such as `H264` or `HEVC`,
+/// carrying the timing and codec extradata needed by the decoder.
+///
+/// This layout is specific to this crate, and tagged as such with the `nndi` magic, as the one
+/// of _NDI|HX_ is not implemented, so such blocks can only be exchanged between `nndi`
+/// sources and sinks.
 #[derive(Debug, Default, PartialEq, BinRead, BinWrite)]
+#[brw(little, magic = b"nndi")]
+pub struct Packet {
+    /// The codec of the data, repeated from the block header.
+    pub fourcc: FourCCVideoType,
+
+    /// Presentation timestamp, in 100ns units.
+    pub pts: i64,
+
+    /// Decoding timestamp, in 100ns units.
+    pub dts: i64,
+
+    /// Whether the data is a keyframe, which does not reference the previous ones.
+    #[br(map = |flags: u32| flags & 1 != 0)]
+    #[bw(map = |keyframe: &bool| u32::from(*keyframe))]
+    pub keyframe: bool,
+
+    #[br(temp)]
+    #[bw(calc = data.len() as u32)]
+    data_size: u32,
+
+    #[br(temp)]
+    #[bw(calc = extradata.len() as u32)]
+    extradata_size: u32,
+
+    /// The compressed data of the frame.
+    #[br(count = data_size)]
+    pub data: Vec<u8>,
+
+    /// The codec extradata (such as `SPS` and `PPS` units), only sent along keyframes.
+    #[br(count = extradata_size)]
+    pub extradata: Vec<u8>,
+}
+
+/// The fixed-size start of a [`Packet`], to inspect it without copying it's data.
+#[derive(BinRead)]
+#[br(little, magic = b"nndi")]
+struct Header {
+    _fourcc: FourCCVideoType,
+    _pts: i64,
+    _dts: i64,
+
+    #[br(map = |flags: u32| flags & 1 != 0)]
+    keyframe: bool,
+}
+
+impl Packet {
+    /// Read whether the packet in `bytes` is a keyframe, without parsing it's data.
+    pub fn is_keyframe(bytes: &[u8]) -> Result<bool> {
+        let header: Header = BinRead::read(&mut std::io::Cursor::new(bytes))?;
+
+        Ok(header.keyframe)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(BinRead::read(&mut std::io::Cursor::new(bytes))?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        self.write(&mut std::io::Cursor::new(&mut bytes))
+            .expect("Failed to write packet to buffer");
+
+        bytes
+    }
+}
+
+/// The scanning of the frame carried in a video block.
+#[derive(Debug, Default, Clone, Copy, PartialEq, BinRead, BinWrite)]
 #[brw(repr = u32)]
 pub enum FrameFormat {
+    /// Both fields interleaved in the frame.
     Interleaved = 0,
 
+    /// A progressive frame.
     #[default]
     Progressive,
 
+    /// Only the first field.
     Field0,
 
+    /// Only the second field.
     Field1,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, BinRead, BinWrite)]
+/// A timestamp on the wire, with a `100ns` precision.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, BinRead, BinWrite)]
 pub struct Timestamp(
     #[br(try_map = |timestamp: i64| chrono::DateTime::from_timestamp_micros(timestamp / 10).ok_or("Timestamp out-of-range"))]
     #[bw(map = |timestamp| timestamp.timestamp_micros() * 10)]
@@ -80,3 +255,25 @@ impl std::convert::From<chrono::DateTime<Utc>> for Timestamp {
         Self(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_roundtrips_packets() -> Result {
+        let packet = Packet {
+            fourcc: FourCCVideoType::H264,
+            pts: 400_000,
+            dts: 0,
+            keyframe: true,
+            data: vec![0, 0, 0, 1, 0x65, 0x88],
+            extradata: vec![0, 0, 0, 1, 0x67, 0x42],
+        };
+
+        assert_eq!(Packet::from_bytes(&packet.to_bytes())?, packet);
+        assert!(Packet::is_keyframe(&packet.to_bytes())?);
+
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@ mod scrambler;
 pub use scrambler::Scrambler;
 
 mod stream;
-pub use stream::Stream;
+pub use stream::{Reader, Stream, Writer};
 
 pub mod frame;
 
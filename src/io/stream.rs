@@ -1,6 +1,11 @@
+use std::net::SocketAddr;
+
 use tokio::{
-    io::{AsyncWriteExt, BufStream},
-    net::TcpStream,
+    io::{AsyncWriteExt, BufReader, BufWriter},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
 };
 
 use super::{
@@ -11,18 +16,58 @@ use crate::Result;
 
 #[derive(Debug)]
 pub struct Stream {
-    stream: BufStream<TcpStream>,
+    reader: Reader,
+    writer: Writer,
 }
 
 impl Stream {
     pub async fn recv(&mut self) -> Result<Frame> {
-        Packet::read(&mut self.stream).await?.into_frame()
+        self.reader.recv().await
     }
 
     pub async fn send(&mut self, frame: &Frame) -> Result {
-        Packet::from_frame(frame).write(&mut self.stream).await?;
+        self.writer.send(frame).await
+    }
 
-        Ok(self.stream.flush().await?)
+    /// Retrieve the next message and convert it to [`Metadata`] if possible, discarding otherwise.
+    pub async fn metadata(&mut self) -> Result<Option<Metadata>> {
+        self.reader.metadata().await
+    }
+
+    pub fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        self.reader.peer_addr()
+    }
+
+    /// Split the stream in it's receiving and sending halves, to use them concurrently.
+    pub fn into_split(self) -> (Reader, Writer) {
+        (self.reader, self.writer)
+    }
+}
+
+impl std::convert::From<TcpStream> for Stream {
+    fn from(stream: TcpStream) -> Self {
+        let (reader, writer) = stream.into_split();
+
+        Self {
+            reader: Reader {
+                stream: BufReader::new(reader),
+            },
+            writer: Writer {
+                stream: BufWriter::new(writer),
+            },
+        }
+    }
+}
+
+/// The receiving half of a [`Stream`].
+#[derive(Debug)]
+pub struct Reader {
+    stream: BufReader<OwnedReadHalf>,
+}
+
+impl Reader {
+    pub async fn recv(&mut self) -> Result<Frame> {
+        Packet::read(&mut self.stream).await?.into_frame()
     }
 
     /// Retrieve the next message and convert it to [`Metadata`] if possible, discarding otherwise.
@@ -50,20 +95,22 @@ impl Stream {
             _ => Ok(None),
         }
     }
-}
 
-impl std::convert::From<TcpStream> for Stream {
-    fn from(stream: TcpStream) -> Self {
-        Self {
-            stream: BufStream::new(stream),
-        }
+    pub fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        self.stream.get_ref().peer_addr()
     }
 }
 
-impl std::ops::Deref for Stream {
-    type Target = TcpStream;
+/// The sending half of a [`Stream`].
+#[derive(Debug)]
+pub struct Writer {
+    stream: BufWriter<OwnedWriteHalf>,
+}
+
+impl Writer {
+    pub async fn send(&mut self, frame: &Frame) -> Result {
+        Packet::from_frame(frame).write(&mut self.stream).await?;
 
-    fn deref(&self) -> &Self::Target {
-        self.stream.get_ref()
+        Ok(self.stream.flush().await?)
     }
 }
@@ -25,29 +25,56 @@ fn hostname() -> String {
     String::from_utf8_lossy(&hostname.into_encoded_bytes()).to_string()
 }
 
-fn name(source: &str) -> String {
+fn machine() -> String {
     let mut hostname = hostname();
     hostname.make_ascii_uppercase();
 
-    format!("{hostname} ({source})")
+    hostname
+}
+
+fn name(source: &str) -> String {
+    format!("{} ({source})", machine())
 }
 
 mod io;
 
+pub mod discovery;
+
 mod error;
 pub use error::{Error, Result};
 
-mod scan;
-pub use scan::Scan;
+pub mod scan;
+pub use scan::{Scan, SourceInfo};
 
 pub mod sink;
 pub use sink::Sink;
 
+pub mod record;
+pub use record::Recorder;
+
+pub mod playout;
+pub use playout::Playout;
+
 pub mod source;
 pub use source::Source;
 
+pub mod audio {
+    //! Audio blocks specifications, for uncompressed samples.
+
+    pub use crate::io::frame::audio::{FourCCAudioType, Spec};
+}
+
+pub mod video {
+    //! Video blocks specifications, for compressed frames.
+
+    pub use crate::io::frame::video::{FourCCVideoType, FrameFormat, Spec, Timestamp, TIMEBASE};
+}
+
 pub mod metadata {
     //! Metadata entries for the NDI sources.
 
-    pub use crate::io::frame::text::{EnabledStreams, Identify, Tally, Version, VideoQuality};
+    pub use crate::io::frame::text::{
+        Connection, ConnectionFeedback, ConnectionState, EnabledStreams, Identify, Message,
+        Metadata, Tally, Version, Video, VideoQuality,
+    };
 }
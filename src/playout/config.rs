@@ -0,0 +1,17 @@
+use std::path::PathBuf;
+
+#[cfg(doc)]
+use super::Playout;
+
+/// Configuration for the [`Playout`] structure.
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    /// Path of the media file to play out, in any format `libavformat` can demux.
+    pub path: PathBuf,
+
+    /// Start over from the beginning of the file once it ended, see [`Playout::set_looping`].
+    pub looping: bool,
+
+    /// Send the video packets as-is when the file is already SpeedHQ, skipping the decoding and encoding.
+    pub passthrough: bool,
+}
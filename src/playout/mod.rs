@@ -0,0 +1,704 @@
+//! Play out of media files as [`Source`]s, at the pace of their timestamps.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ffmpeg::{codec, format, frame, media, Rational};
+use tokio::{runtime::Handle, task::JoinHandle};
+
+use crate::{video, Error, Result, Source};
+
+mod config;
+pub use config::Config;
+
+/// The framerate assumed for the video streams which do not advertise one.
+const FALLBACK_FRAMERATE: Rational = Rational(30, 1);
+
+/// A media file played out to the peers of a [`Source`], running in the background.
+///
+/// The file is demuxed and decoded with `libavformat` and `libavcodec`, and it's frames are sent
+/// with [`Source::broadcast_video`] and [`Source::broadcast_audio`] as their timestamps come due.
+///
+/// Dropping the playout stops it after the frame being sent.
+pub struct Playout {
+    commands: flume::Sender<Command>,
+    task: JoinHandle<Result>,
+}
+
+impl Playout {
+    /// Start playing out the file designated by the `config` to the peers of the `source`.
+    ///
+    /// The file is opened before returning, so that it's errors are reported early.
+    /// This must be called from within a _tokio_ runtime.
+    pub fn start(source: Arc<Source>, config: Config) -> Result<Self> {
+        let player = Player::new(source, config, Handle::current())?;
+        let (commands, commandsrx) = flume::unbounded();
+
+        let task = tokio::task::spawn_blocking(move || player.run(commandsrx));
+
+        Ok(Self { commands, task })
+    }
+
+    /// Seek the playout to the provided `position` from the start of the file.
+    ///
+    /// The playout resumes from the keyframe preceding the `position`.
+    pub fn seek(&self, position: Duration) -> Result {
+        self.commands
+            .send(Command::Seek(position))
+            .map_err(|_| Error::ClosedChannel)
+    }
+
+    /// Set whether the playout starts over from the beginning of the file once it ended.
+    pub fn set_looping(&self, looping: bool) -> Result {
+        self.commands
+            .send(Command::Looping(looping))
+            .map_err(|_| Error::ClosedChannel)
+    }
+
+    /// Stop the playout, after the frame being sent.
+    pub async fn stop(self) -> Result {
+        drop(self.commands);
+
+        self.task.await?
+    }
+
+    /// Wait for the playout to reach the end of a file that is not looping.
+    pub async fn finished(self) -> Result {
+        let Self { commands, task } = self;
+
+        let result = task.await?;
+        drop(commands);
+
+        result
+    }
+}
+
+/// A control command sent to the player.
+enum Command {
+    Seek(Duration),
+    Looping(bool),
+}
+
+/// What to do with the frames after pacing them.
+enum Flow {
+    /// Send the frame, and carry on.
+    Play,
+
+    /// Discard the frame, the player seeked elsewhere.
+    Skip,
+
+    /// Discard the frame, and stop the player.
+    Stop,
+}
+
+/// The wall clock on which the frames are paced.
+#[derive(Default)]
+struct Clock {
+    /// The instant at which the playout started or resumed, and the position it resumed from, in seconds.
+    origin: Option<(Instant, f64)>,
+}
+
+impl Clock {
+    /// Restart the clock from the next frame.
+    fn reset(&mut self) {
+        self.origin = None;
+    }
+
+    /// Wait for the frame at `position` to come due, or for a command to be received.
+    fn wait(
+        &mut self,
+        position: f64,
+        commands: &flume::Receiver<Command>,
+    ) -> std::result::Result<Option<Command>, flume::RecvTimeoutError> {
+        let (start, base) = *self
+            .origin
+            .get_or_insert_with(|| (Instant::now(), position));
+        let due = start + Duration::from_secs_f64((position - base).max(0.0));
+
+        match commands.recv_deadline(due) {
+            Ok(command) => Ok(Some(command)),
+            Err(flume::RecvTimeoutError::Timeout) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+struct VideoTrack {
+    index: usize,
+    time_base: Rational,
+    framerate: Rational,
+    decoder: codec::decoder::Video,
+
+    /// The format to send the packets as-is with, when passing them through.
+    passthrough: Option<video::FourCCVideoType>,
+}
+
+struct AudioTrack {
+    index: usize,
+    time_base: Rational,
+    decoder: codec::decoder::Audio,
+}
+
+/// Demux, decode and send the frames of the file to the source.
+struct Player {
+    source: Arc<Source>,
+    config: Config,
+    handle: Handle,
+    input: format::context::Input,
+    video: Option<VideoTrack>,
+    audio: Option<AudioTrack>,
+    clock: Clock,
+}
+
+impl Player {
+    fn new(source: Arc<Source>, config: Config, handle: Handle) -> Result<Self> {
+        let input = format::input(&config.path)?;
+
+        let video = match input.streams().best(media::Type::Video) {
+            Some(stream) => {
+                let decoder = codec::context::Context::from_parameters(stream.parameters())?
+                    .decoder()
+                    .video()?;
+
+                let framerate = [stream.avg_frame_rate(), stream.rate()]
+                    .into_iter()
+                    .find(|rate| rate.numerator() > 0 && rate.denominator() > 0)
+                    .unwrap_or(FALLBACK_FRAMERATE);
+
+                let passthrough = if config.passthrough && decoder.id() == codec::Id::SPEEDHQ {
+                    // SAFETY: The parameters pointer is owned by the stream,
+                    // which outlives this read of it's `codec_tag`.
+                    let tag = unsafe { (*stream.parameters().as_ptr()).codec_tag };
+
+                    [video::FourCCVideoType::SHQ2, video::FourCCVideoType::SHQ7]
+                        .into_iter()
+                        .find(|fourcc| fourcc.to_code() == tag)
+                } else {
+                    None
+                };
+
+                if config.passthrough && passthrough.is_none() {
+                    tracing::debug!(
+                        "The video of `{}` is not in a SpeedHQ format sent on the wire, decoding it",
+                        config.path.display()
+                    );
+                }
+
+                Some(VideoTrack {
+                    index: stream.index(),
+                    time_base: stream.time_base(),
+                    framerate,
+                    decoder,
+                    passthrough,
+                })
+            }
+            None => None,
+        };
+
+        let audio = match input.streams().best(media::Type::Audio) {
+            Some(stream) => Some(AudioTrack {
+                index: stream.index(),
+                time_base: stream.time_base(),
+                decoder: codec::context::Context::from_parameters(stream.parameters())?
+                    .decoder()
+                    .audio()?,
+            }),
+            None => None,
+        };
+
+        if video.is_none() && audio.is_none() {
+            return Err(ffmpeg::Error::StreamNotFound.into());
+        }
+
+        Ok(Self {
+            source,
+            config,
+            handle,
+            input,
+            video,
+            audio,
+            clock: Clock::default(),
+        })
+    }
+
+    /// Play the file until it ends without looping, or until the `commands` channel is closed.
+    fn run(mut self, commands: flume::Receiver<Command>) -> Result {
+        loop {
+            let mut packet = ffmpeg::Packet::empty();
+
+            let flow = match packet.read(&mut self.input) {
+                Ok(()) => self.play(&packet, &commands)?,
+                Err(ffmpeg::Error::Eof) => match self.drain(&commands)? {
+                    Flow::Play if self.config.looping => {
+                        self.seek(Duration::ZERO)?;
+
+                        Flow::Skip
+                    }
+                    Flow::Play => Flow::Stop,
+                    flow => flow,
+                },
+                Err(err) => return Err(err.into()),
+            };
+
+            if let Flow::Stop = flow {
+                break Ok(());
+            }
+        }
+    }
+
+    /// Seek the input to `position`, and reset the decoders and the clock.
+    fn seek(&mut self, position: Duration) -> Result {
+        let timestamp = i64::try_from(position.as_micros()).unwrap_or(i64::MAX);
+        self.input.seek(timestamp, ..timestamp)?;
+
+        if let Some(track) = &mut self.video {
+            track.decoder.flush();
+        }
+        if let Some(track) = &mut self.audio {
+            track.decoder.flush();
+        }
+        self.clock.reset();
+
+        Ok(())
+    }
+
+    /// Wait for the frame at `position` to come due, handling the commands received meanwhile.
+    fn pace(&mut self, position: f64, commands: &flume::Receiver<Command>) -> Result<Flow> {
+        loop {
+            match self.clock.wait(position, commands) {
+                Ok(None) => return Ok(Flow::Play),
+                Ok(Some(Command::Looping(looping))) => self.config.looping = looping,
+                Ok(Some(Command::Seek(position))) => {
+                    self.seek(position)?;
+
+                    return Ok(Flow::Skip);
+                }
+                Err(_) => return Ok(Flow::Stop),
+            }
+        }
+    }
+
+    /// Send or decode a demuxed `packet`, depending on the track it belongs to.
+    fn play(
+        &mut self,
+        packet: &ffmpeg::Packet,
+        commands: &flume::Receiver<Command>,
+    ) -> Result<Flow> {
+        let index = packet.stream();
+
+        if let Some(track) = self.video.as_mut().filter(|track| track.index == index) {
+            if let Some(fourcc) = track.passthrough {
+                let position = packet.pts().or(packet.dts()).unwrap_or_default() as f64
+                    * f64::from(track.time_base);
+                let mut spec = video::Spec {
+                    fourcc,
+                    width: track.decoder.width(),
+                    height: track.decoder.height(),
+                    fps_num: track.framerate.numerator() as u32,
+                    fps_den: track.framerate.denominator() as u32,
+                    ..Default::default()
+                };
+
+                let flow = self.pace(position, commands)?;
+                if let Flow::Play = flow {
+                    spec.timestamp = chrono::Utc::now().into();
+
+                    if let Err(err) = self
+                        .handle
+                        .block_on(self.source.broadcast_video_packet(packet, spec))
+                    {
+                        tracing::warn!("Unable to send a video packet: {err}");
+                    }
+                }
+
+                return Ok(flow);
+            }
+
+            if let Err(err) = track.decoder.send_packet(packet) {
+                tracing::warn!("Unable to decode a video packet: {err}");
+
+                return Ok(Flow::Play);
+            }
+
+            return self.present_video(commands);
+        }
+
+        if let Some(track) = self.audio.as_mut().filter(|track| track.index == index) {
+            if let Err(err) = track.decoder.send_packet(packet) {
+                tracing::warn!("Unable to decode an audio packet: {err}");
+
+                return Ok(Flow::Play);
+            }
+
+            return self.present_audio(commands);
+        }
+
+        Ok(Flow::Play)
+    }
+
+    /// Send the frames still held by the decoders at the end of the file.
+    fn drain(&mut self, commands: &flume::Receiver<Command>) -> Result<Flow> {
+        if let Some(track) = self
+            .video
+            .as_mut()
+            .filter(|track| track.passthrough.is_none())
+        {
+            // Errors are ignored, as the decoder may already be drained.
+            let _ = track.decoder.send_eof();
+
+            match self.present_video(commands)? {
+                Flow::Play => (),
+                flow => return Ok(flow),
+            }
+        }
+
+        if let Some(track) = &mut self.audio {
+            let _ = track.decoder.send_eof();
+
+            return self.present_audio(commands);
+        }
+
+        Ok(Flow::Play)
+    }
+
+    /// Pace and send the decoded video frames.
+    fn present_video(&mut self, commands: &flume::Receiver<Command>) -> Result<Flow> {
+        loop {
+            let Some(track) = &mut self.video else {
+                return Ok(Flow::Play);
+            };
+
+            let mut decoded = frame::Video::empty();
+            if track.decoder.receive_frame(&mut decoded).is_err() {
+                return Ok(Flow::Play);
+            }
+
+            let position =
+                decoded.timestamp().unwrap_or_default() as f64 * f64::from(track.time_base);
+            let framerate = track.framerate;
+
+            match self.pace(position, commands)? {
+                Flow::Play => (),
+                flow => return Ok(flow),
+            }
+
+            if let Err(err) = self
+                .handle
+                .block_on(self.source.broadcast_video(&decoded, framerate))
+            {
+                tracing::warn!("Unable to send a video frame: {err}");
+            }
+        }
+    }
+
+    /// Pace and send the decoded audio frames.
+    fn present_audio(&mut self, commands: &flume::Receiver<Command>) -> Result<Flow> {
+        loop {
+            let Some(track) = &mut self.audio else {
+                return Ok(Flow::Play);
+            };
+
+            let mut decoded = frame::Audio::empty();
+            if track.decoder.receive_frame(&mut decoded).is_err() {
+                return Ok(Flow::Play);
+            }
+
+            let position =
+                decoded.timestamp().unwrap_or_default() as f64 * f64::from(track.time_base);
+
+            match self.pace(position, commands)? {
+                Flow::Play => (),
+                flow => return Ok(flow),
+            }
+
+            if let Err(err) = self.handle.block_on(self.source.broadcast_audio(&decoded)) {
+                tracing::warn!("Unable to send an audio frame: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::Ipv4Addr,
+        path::{Path, PathBuf},
+    };
+
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{discovery, sink, Sink};
+
+    const RATE: u32 = 48_000;
+
+    /// The time allowed for a playout to end, well beyond the duration of the played files.
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Write a `.wav` file of `duration` of silent 16-bit stereo samples, named after the test.
+    fn generate(name: &str, duration: Duration) -> std::io::Result<PathBuf> {
+        let path = std::env::temp_dir().join(format!("nndi-{name}-{}.wav", std::process::id()));
+
+        let size = (duration.as_secs_f64() * RATE as f64) as u32 * 4;
+        let header = [
+            &b"RIFF"[..],
+            &(36 + size).to_le_bytes(),
+            b"WAVEfmt ",
+            &16u32.to_le_bytes(),
+            &1u16.to_le_bytes(),
+            &2u16.to_le_bytes(),
+            &RATE.to_le_bytes(),
+            &(RATE * 4).to_le_bytes(),
+            &4u16.to_le_bytes(),
+            &16u16.to_le_bytes(),
+            b"data",
+            &size.to_le_bytes(),
+        ]
+        .concat();
+
+        std::fs::write(&path, [header, vec![0; size as usize]].concat())?;
+
+        Ok(path)
+    }
+
+    /// Write a `.mov` file of `count` SpeedHQ frames of 64x32 at 25fps, named after the test.
+    fn clip(name: &str, count: i64) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let path = std::env::temp_dir().join(format!("nndi-{name}-{}.mov", std::process::id()));
+        let time_base = Rational(1, 25);
+
+        let mut context = codec::Context::new().encoder().video()?;
+        context.set_format(format::Pixel::YUV422P);
+        context.set_width(64);
+        context.set_height(32);
+        context.set_time_base(time_base);
+        let mut encoder = context.open_as(
+            codec::encoder::find(codec::Id::SPEEDHQ).ok_or(ffmpeg::Error::EncoderNotFound)?,
+        )?;
+
+        let mut output = format::output(&path)?;
+        let mut parameters = codec::Parameters::new();
+        // SAFETY: The parameters were allocated just before, and are exclusively owned here.
+        unsafe {
+            let par = parameters.as_mut_ptr();
+
+            (*par).codec_type = ffmpeg::ffi::AVMediaType::AVMEDIA_TYPE_VIDEO;
+            (*par).codec_id = codec::Id::SPEEDHQ.into();
+            (*par).codec_tag = video::FourCCVideoType::SHQ2.to_code();
+            (*par).width = 64;
+            (*par).height = 32;
+            (*par).format = ffmpeg::ffi::AVPixelFormat::from(format::Pixel::YUV422P) as i32;
+        }
+        let mut stream = output.add_stream(codec::Id::SPEEDHQ)?;
+        stream.set_time_base(time_base);
+        stream.set_parameters(parameters);
+
+        output.write_header()?;
+        let stream_time_base = output
+            .stream(0)
+            .ok_or(ffmpeg::Error::StreamNotFound)?
+            .time_base();
+
+        let write = |encoder: &mut codec::encoder::Video,
+                     output: &mut format::context::Output|
+         -> Result<(), ffmpeg::Error> {
+            let mut packet = ffmpeg::Packet::empty();
+            while encoder.receive_packet(&mut packet).is_ok() {
+                packet.set_stream(0);
+                packet.rescale_ts(time_base, stream_time_base);
+                packet.write_interleaved(output)?;
+            }
+
+            Ok(())
+        };
+
+        for index in 0..count {
+            let mut frame = frame::Video::new(format::Pixel::YUV422P, 64, 32);
+            for plane in 0..frame.planes() {
+                frame.data_mut(plane).fill(index as u8);
+            }
+            frame.set_pts(Some(index));
+
+            encoder.send_frame(&frame)?;
+            write(&mut encoder, &mut output)?;
+        }
+        encoder.send_eof()?;
+        write(&mut encoder, &mut output)?;
+
+        output.write_trailer()?;
+
+        Ok(path)
+    }
+
+    /// Play the file at `path` to a [`Source`], with a [`Sink`] connected to it beforehand.
+    async fn play(
+        path: &Path,
+        looping: bool,
+        passthrough: bool,
+    ) -> Result<(Playout, Sink, discovery::Server), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let port = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+            .local_addr()?
+            .port();
+
+        let server = discovery::Server::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let source = Source::new(source::Config {
+            name: "playout".into(),
+            discovery: Some(server.local_addr()),
+            bind: Some(Ipv4Addr::LOCALHOST.into()),
+            ports: Some(port..=port),
+            ..Default::default()
+        })
+        .await?;
+
+        let sink = Sink::connect(
+            (Ipv4Addr::LOCALHOST, port),
+            sink::Config {
+                video_queue: 256,
+                audio_queue: 256,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        // Wait for the source to register the sink, so that it receives the first frames
+        tokio::time::timeout(TIMEOUT, async {
+            while source.peers().await.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await?;
+
+        let playout = Playout::start(
+            Arc::new(source),
+            Config {
+                path: path.into(),
+                looping,
+                passthrough,
+            },
+        )?;
+
+        Ok((playout, sink, server))
+    }
+
+    /// Check that the whole `clip` of `count` frames is received by the `sink`.
+    async fn receive(sink: &Sink, count: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let packets: Vec<_> = tokio::time::timeout(
+            TIMEOUT,
+            sink.video_packet_stream().take(count).collect::<Vec<_>>(),
+        )
+        .await?
+        .into_iter()
+        .collect::<Result<_>>()?;
+
+        assert_eq!(packets.len(), count);
+        for packet in packets {
+            assert_eq!(packet.spec.fourcc, video::FourCCVideoType::SHQ2);
+            assert_eq!((packet.spec.width, packet.spec.height), (64, 32));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_paces_the_frames() -> Result<(), Box<dyn std::error::Error>> {
+        let path = generate("pacing", Duration::from_secs(1))?;
+        let (playout, _sink, _server) = play(&path, false, false).await?;
+
+        let start = Instant::now();
+        tokio::time::timeout(TIMEOUT, playout.finished()).await??;
+        let elapsed = start.elapsed();
+
+        // The last frame is due a frame before the end of the file, and is never sent early
+        assert!(elapsed >= Duration::from_millis(900), "{elapsed:?}");
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_decodes_and_sends_the_video() -> Result<(), Box<dyn std::error::Error>> {
+        let path = clip("video", 25)?;
+        let (playout, sink, _server) = play(&path, false, false).await?;
+
+        let start = Instant::now();
+        tokio::time::timeout(TIMEOUT, playout.finished()).await??;
+        assert!(start.elapsed() >= Duration::from_millis(900));
+
+        receive(&sink, 25).await?;
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_passes_the_speedhq_packets_through() -> Result<(), Box<dyn std::error::Error>> {
+        let path = clip("passthrough", 25)?;
+        let (playout, sink, _server) = play(&path, false, true).await?;
+
+        let start = Instant::now();
+        tokio::time::timeout(TIMEOUT, playout.finished()).await??;
+        assert!(start.elapsed() >= Duration::from_millis(900));
+
+        receive(&sink, 25).await?;
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_seeks_in_the_file() -> Result<(), Box<dyn std::error::Error>> {
+        let path = generate("seek", Duration::from_secs(30))?;
+        let (playout, _sink, _server) = play(&path, false, false).await?;
+
+        // Only the last second is played, well within the timeout
+        playout.seek(Duration::from_secs(29))?;
+        tokio::time::timeout(TIMEOUT, playout.finished()).await??;
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_loops_over_the_file() -> Result<(), Box<dyn std::error::Error>> {
+        let path = generate("looping", Duration::from_secs(1))?;
+        let (playout, _sink, _server) = play(&path, true, false).await?;
+
+        let start = Instant::now();
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+
+        // The playout ends with the loop in progress, at the end of the second one
+        playout.set_looping(false)?;
+        tokio::time::timeout(TIMEOUT, playout.finished()).await??;
+        let elapsed = start.elapsed();
+
+        assert!(elapsed >= Duration::from_millis(1900), "{elapsed:?}");
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_stops_after_the_frame_being_sent() -> Result<(), Box<dyn std::error::Error>> {
+        let path = generate("stop", Duration::from_secs(30))?;
+        let (playout, _sink, _server) = play(&path, true, false).await?;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // The file is far longer than the timeout, so the playout must have been stopped
+        tokio::time::timeout(TIMEOUT, playout.stop()).await??;
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use std::{path::PathBuf, time::Duration};
+
+#[cfg(doc)]
+use super::Recorder;
+
+/// Configuration for the [`Recorder`] structure.
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    /// Path of the file to record to, suffixed with the index of the file when splitting,
+    /// such as `camera-001.mkv` for `camera.mkv`.
+    pub path: PathBuf,
+
+    /// Container of the files, guessed from the extension of the `path` by default.
+    pub container: Option<Container>,
+
+    /// Start a new file once the current one lasts longer than `max_duration`.
+    pub max_duration: Option<Duration>,
+
+    /// Start a new file once the current one grows larger than `max_size` bytes.
+    pub max_size: Option<u64>,
+}
+
+impl Config {
+    /// The container of the files, either configured or guessed from the extension of the `path`.
+    pub(super) fn container(&self) -> Container {
+        self.container.unwrap_or_else(
+            || match self.path.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) if ext.eq_ignore_ascii_case("mov") => Container::Mov,
+                _ => Container::Matroska,
+            },
+        )
+    }
+
+    /// Whether the files are split by duration or size, and suffixed with their index.
+    pub(super) fn splits(&self) -> bool {
+        self.max_duration.is_some() || self.max_size.is_some()
+    }
+
+    /// The path of the file with the provided `index`.
+    pub(super) fn path(&self, index: usize) -> PathBuf {
+        if !self.splits() {
+            return self.path.clone();
+        }
+
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+        let name = match self.path.extension() {
+            Some(ext) => format!("{stem}-{index:03}.{}", ext.to_string_lossy()),
+            None => format!("{stem}-{index:03}"),
+        };
+
+        self.path.with_file_name(name)
+    }
+}
+
+/// Container of the recorded files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// Matroska, `.mkv`, which carries SpeedHQ as-is.
+    Matroska,
+
+    /// QuickTime, `.mov`.
+    Mov,
+}
+
+impl Container {
+    /// The name of the `libavformat` muxer for the container.
+    pub(super) fn muxer(&self) -> &'static str {
+        match self {
+            Self::Matroska => "matroska",
+            Self::Mov => "mov",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_suffixes_split_files() {
+        let mut config = Config {
+            path: "/records/camera.mkv".into(),
+            ..Default::default()
+        };
+
+        assert_eq!(config.path(1), PathBuf::from("/records/camera.mkv"));
+
+        config.max_duration = Some(Duration::from_secs(3600));
+
+        assert_eq!(config.path(1), PathBuf::from("/records/camera-001.mkv"));
+        assert_eq!(config.container(), Container::Matroska);
+    }
+}
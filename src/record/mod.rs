@@ -0,0 +1,83 @@
+//! Recording of [`Sink`]s to files, without re-encoding.
+
+use futures::StreamExt;
+use tokio::task::JoinHandle;
+
+use crate::{Result, Sink};
+
+mod config;
+pub use config::{Config, Container};
+
+mod muxer;
+use muxer::{Item, Muxer};
+
+/// The capacity of the queue of packets waiting to be muxed, before the [`Sink`] queues
+/// fill up and drop the incoming packets when the disk cannot keep up.
+const ITEMS: usize = 64;
+
+/// A recording of a [`Sink`] to _Matroska_ or _QuickTime_ files, running in the background.
+///
+/// The received video and audio packets are muxed as-is, timed from the [`crate::video::Timestamp`]
+/// of the frames, and their per-frame metadata is stored in a text subtitle track.
+///
+/// The recorder takes ownership of the [`Sink`] to be the only consumer of it's packets,
+/// as the packets received by any clone of it made beforehand are missing from the recording.
+pub struct Recorder {
+    forward: JoinHandle<()>,
+    muxer: JoinHandle<Result>,
+}
+
+impl Recorder {
+    /// Start recording the `sink` to files according to the provided `config`.
+    pub fn start(sink: Sink, config: Config) -> Self {
+        let (items, itemsrx) = flume::bounded(ITEMS);
+
+        let forward = tokio::spawn(Self::forward(sink, items));
+        let muxer = tokio::task::spawn_blocking(move || Muxer::new(config).run(itemsrx));
+
+        Self { forward, muxer }
+    }
+
+    /// Forward the packets of the `sink` to the muxer, stamped with the time of their reception.
+    async fn forward(sink: Sink, items: flume::Sender<Item>) {
+        let mut video = std::pin::pin!(sink.video_packet_stream());
+        let mut audio = std::pin::pin!(sink.audio_packet_stream());
+
+        loop {
+            let item = tokio::select! {
+                Some(packet) = video.next() => match packet {
+                    Ok(packet) => Item::Video(packet, chrono::Utc::now()),
+                    Err(err) => {
+                        tracing::warn!("Unable to record a video packet: {err}");
+
+                        continue;
+                    }
+                },
+                Some(packet) = audio.next() => Item::Audio(packet, chrono::Utc::now()),
+                else => break,
+            };
+
+            if items.send_async(item).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Stop the recording, and finalize the current file.
+    pub async fn stop(mut self) -> Result {
+        self.forward.abort();
+
+        (&mut self.muxer).await?
+    }
+
+    /// Wait for the recording to end when the source disconnects, and finalize the current file.
+    pub async fn finished(mut self) -> Result {
+        (&mut self.muxer).await?
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.forward.abort();
+    }
+}
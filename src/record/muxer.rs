@@ -0,0 +1,981 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use ffmpeg::{codec, ffi, format, Rational};
+
+use crate::{
+    io::frame::{audio, video},
+    sink::{AudioPacket, VideoPacket},
+    Result,
+};
+
+use super::{Config, Container};
+
+/// The time allowed to receive both video and audio before opening a file, to know the tracks it holds.
+const PROBE: Duration = Duration::from_secs(1);
+
+/// The minimum drift of the audio timestamps from the arrival times before they are resynchronized,
+/// in fractions of a second.
+const DRIFT: i64 = 20;
+
+/// A packet received from the `Sink`, with the local time of it's reception.
+pub(super) enum Item {
+    Video(VideoPacket, DateTime<Utc>),
+    Audio(AudioPacket, DateTime<Utc>),
+}
+
+/// Mux the received packets to files, splitting them as configured.
+pub(super) struct Muxer {
+    config: Config,
+    index: usize,
+    file: Option<File>,
+    pending: Vec<Item>,
+
+    /// The audio stream which started after the video file was opened, added at the next keyframe.
+    late: Option<audio::Spec>,
+
+    /// The offset of the clock of the source from the local one, to time the audio with the video.
+    clock: TimeDelta,
+}
+
+impl Muxer {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            index: 0,
+            file: None,
+            pending: Vec::new(),
+            late: None,
+            clock: TimeDelta::zero(),
+        }
+    }
+
+    /// Mux the `items` until the channel is closed, and finalize the last file.
+    pub fn run(mut self, items: flume::Receiver<Item>) -> Result {
+        let mut deadline = None;
+
+        loop {
+            let item = match deadline {
+                Some(deadline) if self.file.is_none() => items.recv_deadline(deadline),
+                _ => items
+                    .recv()
+                    .map_err(|_| flume::RecvTimeoutError::Disconnected),
+            };
+
+            match item {
+                Ok(item) => {
+                    if let Item::Video(packet, received) = &item {
+                        self.clock = *packet.spec.timestamp - *received;
+                    }
+
+                    if self.file.is_some() {
+                        self.write(item)?;
+                        continue;
+                    }
+
+                    deadline.get_or_insert_with(|| Instant::now() + PROBE);
+                    self.pending.push(item);
+
+                    if self.probed() {
+                        self.flush()?;
+                    }
+                }
+                Err(flume::RecvTimeoutError::Timeout) => self.flush()?,
+                Err(flume::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        self.flush()?;
+
+        match self.file.take() {
+            Some(file) => file.close(),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether both video and audio were received, so the tracks of the file are known.
+    fn probed(&self) -> bool {
+        self.pending
+            .iter()
+            .any(|item| matches!(item, Item::Video(..)))
+            && self
+                .pending
+                .iter()
+                .any(|item| matches!(item, Item::Audio(..)))
+    }
+
+    /// Open the first file with the tracks of the pending items, and write them to it.
+    fn flush(&mut self) -> Result {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let pending = std::mem::take(&mut self.pending);
+
+        let video = pending.iter().find_map(|item| match item {
+            Item::Video(packet, _) => Some(VideoParams::from_packet(packet)),
+            _ => None,
+        });
+        let audio = pending.iter().find_map(|item| match item {
+            Item::Audio(packet, _) => Some(packet.spec.clone()),
+            _ => None,
+        });
+        let origin = pending
+            .iter()
+            .map(|item| self.time(item))
+            .min()
+            .unwrap_or_else(Utc::now);
+
+        self.open(video, audio, origin)?;
+
+        for item in pending {
+            self.write(item)?;
+        }
+
+        Ok(())
+    }
+
+    /// The time of the `item`, on the clock of the source.
+    fn time(&self, item: &Item) -> DateTime<Utc> {
+        match item {
+            Item::Video(packet, _) => *packet.spec.timestamp,
+            Item::Audio(_, received) => *received + self.clock,
+        }
+    }
+
+    fn open(
+        &mut self,
+        video: Option<VideoParams>,
+        audio: Option<audio::Spec>,
+        origin: DateTime<Utc>,
+    ) -> Result {
+        if let Some(file) = self.file.take() {
+            file.close()?;
+        }
+
+        self.index += 1;
+        self.file = Some(File::create(
+            &self.config,
+            self.index,
+            video,
+            audio,
+            origin,
+        )?);
+
+        Ok(())
+    }
+
+    fn write(&mut self, item: Item) -> Result {
+        let time = self.time(&item);
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+
+        match &item {
+            Item::Video(packet, _) => {
+                let params = VideoParams::from_packet(packet);
+                let changed = file
+                    .video
+                    .as_ref()
+                    .is_some_and(|track| !track.params.same_format(&params));
+
+                // A stream which started after the file was opened is added to the next one.
+                let late = file.video.is_none() || self.late.is_some();
+
+                // Only split on keyframes, so that each file can be decoded on it's own.
+                if packet.packet.is_key() && (late || changed || file.exceeds(&self.config)) {
+                    if file.video.is_none() {
+                        tracing::info!("A video stream started, splitting the file to record it");
+                    }
+
+                    let audio = self
+                        .late
+                        .take()
+                        .or_else(|| file.audio.as_ref().map(|track| track.spec.clone()));
+
+                    self.open(Some(params), audio, time)?;
+                }
+            }
+            Item::Audio(packet, _) if file.audio.is_none() => {
+                // Wait for a keyframe to split, so that the next file starts with a decodable frame.
+                if self.late.replace(packet.spec.clone()).is_none() {
+                    tracing::info!(
+                        "An audio stream started, splitting the file at the next keyframe to record it"
+                    );
+                }
+
+                return Ok(());
+            }
+            Item::Audio(packet, _) => {
+                let changed = file
+                    .audio
+                    .as_ref()
+                    .is_some_and(|track| !same_audio_format(&track.spec, &packet.spec));
+
+                if changed || (file.video.is_none() && file.exceeds(&self.config)) {
+                    let video = file.video.as_ref().map(|track| track.params.clone());
+
+                    self.open(video, Some(packet.spec.clone()), time)?;
+                }
+            }
+        }
+
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+
+        match item {
+            Item::Video(packet, _) => file.write_video(packet),
+            Item::Audio(packet, _) => file.write_audio(packet, time),
+        }
+    }
+}
+
+/// The parameters of the video track of a file.
+#[derive(Clone)]
+struct VideoParams {
+    fourcc: video::FourCCVideoType,
+    width: u32,
+    height: u32,
+    fps_num: u32,
+    fps_den: u32,
+    extradata: Vec<u8>,
+}
+
+impl VideoParams {
+    fn from_packet(packet: &VideoPacket) -> Self {
+        Self {
+            fourcc: packet.spec.fourcc,
+            width: packet.spec.width,
+            height: packet.spec.height,
+            fps_num: packet.spec.fps_num,
+            fps_den: packet.spec.fps_den,
+            extradata: packet.extradata.clone(),
+        }
+    }
+
+    fn same_format(&self, other: &Self) -> bool {
+        (self.fourcc, self.width, self.height) == (other.fourcc, other.width, other.height)
+    }
+
+    /// The duration of a frame, in [`video::TIMEBASE`] units.
+    fn frame_duration(&self) -> i64 {
+        match self.fps_num {
+            0 => 0,
+            fps_num => self.fps_den as i64 * 10_000_000 / fps_num as i64,
+        }
+    }
+
+    fn parameters(&self) -> Result<codec::Parameters> {
+        let mut parameters = codec::Parameters::new();
+
+        // SAFETY: The parameters were allocated just before, and are exclusively owned here.
+        unsafe {
+            let par = parameters.as_mut_ptr();
+
+            (*par).codec_type = ffi::AVMediaType::AVMEDIA_TYPE_VIDEO;
+            (*par).codec_id = self.fourcc.to_codec().into();
+            (*par).width = self.width as i32;
+            (*par).height = self.height as i32;
+            (*par).format = ffi::AVPixelFormat::from(self.fourcc.to_format()) as i32;
+
+            // SpeedHQ is identified by it's tag in both containers, which tells the variant,
+            // while the muxers pick the tags of the inter-frame codecs themselves.
+            if !self.fourcc.is_inter() {
+                (*par).codec_tag = self.fourcc.to_code();
+            }
+
+            set_extradata(par, &self.extradata)?;
+        }
+
+        Ok(parameters)
+    }
+}
+
+fn same_audio_format(spec: &audio::Spec, other: &audio::Spec) -> bool {
+    (spec.fourcc, spec.num_channels, spec.sample_rate)
+        == (other.fourcc, other.num_channels, other.sample_rate)
+}
+
+fn audio_parameters(spec: &audio::Spec) -> codec::Parameters {
+    let mut parameters = codec::Parameters::new();
+    let sample = spec.fourcc.to_format().packed();
+    let channels = spec.num_channels as i32;
+
+    // SAFETY: The parameters were allocated just before, and are exclusively owned here.
+    unsafe {
+        let par = parameters.as_mut_ptr();
+
+        (*par).codec_type = ffi::AVMediaType::AVMEDIA_TYPE_AUDIO;
+        (*par).codec_id = audio_codec(spec.fourcc).into();
+        (*par).format = ffi::AVSampleFormat::from(sample) as i32;
+        (*par).sample_rate = spec.sample_rate as i32;
+        (*par).channels = channels;
+        (*par).channel_layout = ffmpeg::ChannelLayout::default(channels).bits();
+        (*par).bits_per_coded_sample = sample.bytes() as i32 * 8;
+        (*par).block_align = sample.bytes() as i32 * channels;
+    }
+
+    parameters
+}
+
+/// The PCM codec storing the samples of the `fourcc`, once interleaved.
+fn audio_codec(fourcc: audio::FourCCAudioType) -> codec::Id {
+    match fourcc {
+        audio::FourCCAudioType::FOWT => codec::Id::PCM_F32LE,
+        audio::FourCCAudioType::SOWT => codec::Id::PCM_S16LE,
+    }
+}
+
+/// Interleave the samples of the `packet`, as planar channels are laid out one after the other on the wire.
+fn interleave(packet: &AudioPacket) -> Vec<u8> {
+    let data = packet.packet.data().unwrap_or_default();
+    let sample = packet.spec.fourcc.to_format();
+
+    if sample.is_packed() {
+        return data.to_vec();
+    }
+
+    let size = sample.bytes();
+    let channels = packet.spec.num_channels as usize;
+    let samples = packet.spec.samples as usize;
+
+    let mut interleaved = Vec::with_capacity(data.len());
+    for idx in 0..samples {
+        for channel in 0..channels {
+            let offset = (channel * samples + idx) * size;
+
+            if let Some(bytes) = data.get(offset..offset + size) {
+                interleaved.extend_from_slice(bytes);
+            }
+        }
+    }
+
+    interleaved
+}
+
+/// Copy `data` to the `extradata` of the parameters, in a padded buffer owned by `libavcodec`.
+///
+/// # Safety
+///
+/// The `par` pointer must point to valid parameters, without any `extradata`.
+unsafe fn set_extradata(par: *mut ffi::AVCodecParameters, data: &[u8]) -> Result {
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let buffer =
+        ffi::av_mallocz(data.len() + ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize) as *mut u8;
+    if buffer.is_null() {
+        return Err(ffmpeg::Error::Other {
+            errno: ffmpeg::error::ENOMEM,
+        }
+        .into());
+    }
+
+    std::ptr::copy_nonoverlapping(data.as_ptr(), buffer, data.len());
+    (*par).extradata = buffer;
+    (*par).extradata_size = data.len() as i32;
+
+    Ok(())
+}
+
+struct VideoTrack {
+    params: VideoParams,
+    index: usize,
+    time_base: Rational,
+
+    /// The timestamp of the first packet, and it's offset from the start of the file.
+    first: Option<(i64, i64)>,
+}
+
+struct AudioTrack {
+    spec: audio::Spec,
+    index: usize,
+    time_base: Rational,
+
+    /// The timestamp of the next packet, in samples.
+    next: Option<i64>,
+}
+
+struct SubtitleTrack {
+    index: usize,
+    time_base: Rational,
+}
+
+/// A file being recorded, with it's tracks.
+struct File {
+    output: format::context::Output,
+    container: Container,
+    origin: DateTime<Utc>,
+
+    video: Option<VideoTrack>,
+    audio: Option<AudioTrack>,
+    subtitles: Option<SubtitleTrack>,
+
+    /// The size of the packets written so far.
+    size: u64,
+
+    /// The duration of the file so far, in [`video::TIMEBASE`] units.
+    duration: i64,
+}
+
+impl File {
+    fn create(
+        config: &Config,
+        index: usize,
+        video: Option<VideoParams>,
+        audio: Option<audio::Spec>,
+        origin: DateTime<Utc>,
+    ) -> Result<Self> {
+        let container = config.container();
+        let path = config.path(index);
+        let mut output = format::output_as(&path, container.muxer())?;
+
+        let mut metadata = ffmpeg::Dictionary::new();
+        metadata.set(
+            "creation_time",
+            &origin.to_rfc3339_opts(SecondsFormat::Micros, true),
+        );
+        output.set_metadata(metadata);
+
+        let video = match video {
+            Some(params) => {
+                let mut stream = output.add_stream(params.fourcc.to_codec())?;
+                stream.set_time_base(video::TIMEBASE);
+                stream.set_avg_frame_rate(Rational(params.fps_num as i32, params.fps_den as i32));
+                stream.set_parameters(params.parameters()?);
+
+                Some((stream.index(), params))
+            }
+            None => None,
+        };
+
+        // Per-frame metadata is stored as text subtitles, timed with the video frames.
+        let subtitles = match video {
+            Some(_) => {
+                let id = match container {
+                    Container::Matroska => codec::Id::TEXT,
+                    Container::Mov => codec::Id::MOV_TEXT,
+                };
+
+                let mut stream = output.add_stream(id)?;
+                stream.set_time_base(video::TIMEBASE);
+
+                let mut parameters = codec::Parameters::new();
+                // SAFETY: The parameters were allocated just before, and are exclusively owned here.
+                unsafe {
+                    (*parameters.as_mut_ptr()).codec_type = ffi::AVMediaType::AVMEDIA_TYPE_SUBTITLE;
+                    (*parameters.as_mut_ptr()).codec_id = id.into();
+                }
+                stream.set_parameters(parameters);
+
+                Some(stream.index())
+            }
+            None => None,
+        };
+
+        let audio = match audio {
+            Some(spec) => {
+                let mut stream = output.add_stream(audio_codec(spec.fourcc))?;
+                stream.set_time_base(Rational(1, spec.sample_rate as i32));
+                stream.set_parameters(audio_parameters(&spec));
+
+                Some((stream.index(), spec))
+            }
+            None => None,
+        };
+
+        output.write_header()?;
+
+        // The muxer is free to change the time base of the streams when writing the header.
+        let time_base = |output: &format::context::Output, index: usize| {
+            output
+                .stream(index)
+                .map(|stream| stream.time_base())
+                .ok_or(ffmpeg::Error::StreamNotFound)
+        };
+
+        let video = match video {
+            Some((index, params)) => Some(VideoTrack {
+                params,
+                index,
+                time_base: time_base(&output, index)?,
+                first: None,
+            }),
+            None => None,
+        };
+        let subtitles = match subtitles {
+            Some(index) => Some(SubtitleTrack {
+                index,
+                time_base: time_base(&output, index)?,
+            }),
+            None => None,
+        };
+        let audio = match audio {
+            Some((index, spec)) => Some(AudioTrack {
+                spec,
+                index,
+                time_base: time_base(&output, index)?,
+                next: None,
+            }),
+            None => None,
+        };
+
+        tracing::info!("Started recording to `{}`", path.display());
+
+        Ok(Self {
+            output,
+            container,
+            origin,
+            video,
+            audio,
+            subtitles,
+            size: 0,
+            duration: 0,
+        })
+    }
+
+    /// Whether the file exceeds the configured maximum duration or size.
+    fn exceeds(&self, config: &Config) -> bool {
+        config
+            .max_duration
+            .is_some_and(|max| self.duration >= max.as_micros() as i64 * 10)
+            || config.max_size.is_some_and(|max| self.size >= max)
+    }
+
+    fn write_video(&mut self, packet: VideoPacket) -> Result {
+        let Some(track) = &mut self.video else {
+            return Ok(());
+        };
+
+        let raw_pts = packet.packet.pts().unwrap_or_default();
+        let raw_dts = packet.packet.dts().unwrap_or(raw_pts);
+        let (first, offset) = match track.first {
+            Some(first) => first,
+            // Start the track on a keyframe, as the other ones reference previous frames.
+            None if packet.packet.is_key() => *track.first.insert((
+                raw_pts,
+                (*packet.spec.timestamp - self.origin)
+                    .num_microseconds()
+                    .unwrap_or_default()
+                    * 10,
+            )),
+            None => return Ok(()),
+        };
+
+        let duration = track.params.frame_duration();
+        let pts = offset + raw_pts - first;
+        let dts = offset + raw_dts - first;
+
+        let mut output = packet.packet;
+        output.set_pts(Some(pts));
+        output.set_dts(Some(dts));
+        output.set_duration(duration);
+        output.set_stream(track.index);
+        output.rescale_ts(video::TIMEBASE, track.time_base);
+
+        self.size += output.size() as u64;
+        self.duration = self.duration.max(pts + duration);
+        output.write_interleaved(&mut self.output)?;
+
+        if !packet.spec.metadata.0.is_empty() {
+            self.write_subtitle(
+                pts,
+                duration,
+                &String::from_utf8_lossy(&packet.spec.metadata.0),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn write_subtitle(&mut self, pts: i64, duration: i64, xml: &str) -> Result {
+        let Some(track) = &self.subtitles else {
+            return Ok(());
+        };
+
+        let data = match self.container {
+            Container::Matroska => xml.as_bytes().to_vec(),
+            // QuickTime text samples are prefixed with their length.
+            Container::Mov => {
+                let Ok(len) = u16::try_from(xml.len()) else {
+                    tracing::warn!(
+                        "Skipped a metadata sample of {} bytes, too large for QuickTime",
+                        xml.len()
+                    );
+
+                    return Ok(());
+                };
+
+                [&len.to_be_bytes(), xml.as_bytes()].concat()
+            }
+        };
+
+        let mut packet = ffmpeg::Packet::copy(&data);
+        packet.set_pts(Some(pts));
+        packet.set_dts(Some(pts));
+        packet.set_duration(duration);
+        packet.set_stream(track.index);
+        packet.rescale_ts(video::TIMEBASE, track.time_base);
+
+        self.size += packet.size() as u64;
+        packet.write_interleaved(&mut self.output)?;
+
+        Ok(())
+    }
+
+    fn write_audio(&mut self, packet: AudioPacket, time: DateTime<Utc>) -> Result {
+        let Some(track) = &mut self.audio else {
+            return Ok(());
+        };
+
+        let rate = track.spec.sample_rate as i64;
+        let samples = packet.spec.samples as i64;
+        let arrival =
+            ((time - self.origin).num_microseconds().unwrap_or_default() * rate / 1_000_000).max(0);
+
+        // Follow the arrival times once the running count of samples drifts from them by more than
+        // a block, such as after lost blocks, skipping the blocks ahead to keep the timestamps increasing.
+        let drift = samples.max(rate / DRIFT);
+        let pts = match track.next {
+            Some(next) if next > arrival + drift => {
+                tracing::debug!("Skipped an audio block ahead of it's arrival time");
+
+                return Ok(());
+            }
+            Some(next) if next + drift >= arrival => next,
+            _ => arrival,
+        };
+        track.next = Some(pts + samples);
+
+        let time_base = Rational(1, rate as i32);
+        let mut output = ffmpeg::Packet::copy(&interleave(&packet));
+        output.set_pts(Some(pts));
+        output.set_dts(Some(pts));
+        output.set_duration(samples);
+        output.set_stream(track.index);
+        output.rescale_ts(time_base, track.time_base);
+
+        self.size += output.size() as u64;
+        self.duration = self
+            .duration
+            .max((pts + samples) * 10_000_000 / rate.max(1));
+        output.write_interleaved(&mut self.output)?;
+
+        Ok(())
+    }
+
+    /// Finalize the file, writing it's trailer.
+    fn close(mut self) -> Result {
+        self.output.write_trailer()?;
+
+        tracing::info!(
+            "Finished recording {}s to a file of {} bytes",
+            self.duration / 10_000_000,
+            self.size
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use ffmpeg::Rescale;
+
+    use super::*;
+
+    /// The time base the timestamps are checked in, as Matroska stores them in milliseconds.
+    const MILLIS: Rational = Rational(1, 1_000);
+
+    /// Synthetic SpeedHQ frames and PCM samples every `40ms`, for `count` frames.
+    fn items(count: usize) -> Vec<Item> {
+        let start = Utc::now();
+
+        (0..count)
+            .flat_map(|index| {
+                let time = start + TimeDelta::milliseconds(40 * index as i64);
+
+                let mut packet = ffmpeg::Packet::copy(&[index as u8; 256]);
+                packet.set_pts(Some(time.timestamp_micros() * 10));
+                packet.set_dts(packet.pts());
+                packet.set_flags(codec::packet::Flags::KEY);
+
+                let video = VideoPacket {
+                    spec: video::Spec {
+                        fourcc: video::FourCCVideoType::SHQ2,
+                        width: 64,
+                        height: 32,
+                        fps_num: 25,
+                        fps_den: 1,
+                        timestamp: time.into(),
+                        metadata: format!("<frame index=\"{index}\"/>").as_str().into(),
+                        ..Default::default()
+                    },
+                    packet,
+                    extradata: Vec::new(),
+                };
+                let audio = AudioPacket {
+                    spec: audio::Spec {
+                        fourcc: audio::FourCCAudioType::SOWT,
+                        samples: 1920,
+                        num_channels: 2,
+                        sample_rate: 48_000,
+                    },
+                    packet: ffmpeg::Packet::copy(&[0; 1920 * 2 * 2]),
+                };
+
+                [Item::Video(video, time), Item::Audio(audio, time)]
+            })
+            .collect()
+    }
+
+    fn record(config: &Config, count: usize) -> Result {
+        mux(config, items(count))
+    }
+
+    fn mux(config: &Config, items: Vec<Item>) -> Result {
+        let (sender, receiver) = flume::unbounded();
+        for item in items {
+            sender.send(item).map_err(|_| crate::Error::ClosedChannel)?;
+        }
+        drop(sender);
+
+        Muxer::new(config.clone()).run(receiver)
+    }
+
+    /// The codecs of the tracks of the file at `path`.
+    fn codecs(path: &Path) -> Result<Vec<codec::Id>> {
+        Ok(format::input(path)?
+            .streams()
+            .map(|stream| stream.parameters().id())
+            .collect())
+    }
+
+    /// The video timestamps, in milliseconds, and the subtitles of the file at `path`.
+    fn read(path: &Path) -> Result<(Vec<i64>, Vec<Vec<u8>>), Box<dyn std::error::Error>> {
+        let subtitles = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("mov") => codec::Id::MOV_TEXT,
+            _ => codec::Id::TEXT,
+        };
+
+        assert_eq!(
+            codecs(path)?,
+            [codec::Id::SPEEDHQ, subtitles, codec::Id::PCM_S16LE]
+        );
+
+        let mut input = format::input(path)?;
+
+        let mut timestamps = Vec::new();
+        let mut payloads = Vec::new();
+        for (stream, packet) in input.packets() {
+            match stream.parameters().id() {
+                codec::Id::SPEEDHQ => timestamps.push(
+                    packet
+                        .pts()
+                        .ok_or("No video timestamp")?
+                        .rescale(stream.time_base(), MILLIS),
+                ),
+                id if id == subtitles => payloads.push(packet.data().unwrap_or_default().to_vec()),
+                _ => (),
+            }
+        }
+
+        Ok((timestamps, payloads))
+    }
+
+    fn directory(name: &str) -> Result<std::path::PathBuf, std::io::Error> {
+        let directory = std::env::temp_dir().join(format!("nndi-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+
+        Ok(directory)
+    }
+
+    #[test]
+    fn it_records_and_reads_back_the_packets() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let directory = directory("record")?;
+        let config = Config {
+            path: directory.join("camera.mkv"),
+            ..Default::default()
+        };
+
+        record(&config, 10)?;
+        let (timestamps, subtitles) = read(&config.path(1))?;
+
+        assert_eq!(
+            timestamps,
+            (0..10).map(|index| index * 40).collect::<Vec<_>>()
+        );
+        assert_eq!(subtitles.len(), 10);
+        assert_eq!(subtitles[3], b"<frame index=\"3\"/>");
+
+        std::fs::remove_dir_all(directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_records_speedhq_to_quicktime() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let directory = directory("quicktime")?;
+        let config = Config {
+            path: directory.join("camera.mov"),
+            ..Default::default()
+        };
+
+        record(&config, 10)?;
+        let (timestamps, subtitles) = read(&config.path(1))?;
+
+        assert_eq!(
+            timestamps,
+            (0..10).map(|index| index * 40).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            subtitles[3],
+            [&18u16.to_be_bytes()[..], &b"<frame index=\"3\"/>"[..]].concat()
+        );
+
+        std::fs::remove_dir_all(directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_splits_the_files_on_max_duration() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let directory = directory("duration")?;
+        let config = Config {
+            path: directory.join("camera.mkv"),
+            max_duration: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+
+        // Two seconds of frames, split after the first one
+        record(&config, 50)?;
+
+        let (first, _) = read(&config.path(1))?;
+        let (second, subtitles) = read(&config.path(2))?;
+
+        assert_eq!(first, (0..25).map(|index| index * 40).collect::<Vec<_>>());
+        assert_eq!(second, (0..25).map(|index| index * 40).collect::<Vec<_>>());
+        assert_eq!(subtitles[0], b"<frame index=\"25\"/>");
+        assert!(!config.path(3).exists());
+
+        std::fs::remove_dir_all(directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_splits_the_files_on_max_size() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let directory = directory("size")?;
+        let config = Config {
+            path: directory.join("camera.mkv"),
+            max_size: Some(64 * 1024),
+            ..Default::default()
+        };
+
+        record(&config, 50)?;
+
+        let mut frames = 0;
+        let mut index = 1;
+        while config.path(index).exists() {
+            let (timestamps, _) = read(&config.path(index))?;
+
+            // Each file starts on it's own, with a keyframe
+            assert_eq!(timestamps.first(), Some(&0));
+            frames += timestamps.len();
+            index += 1;
+        }
+
+        // With about 8kB of samples and data per frame, the 64kB are exceeded after 9 frames
+        assert_eq!(index - 1, 6);
+        assert_eq!(frames, 50);
+
+        std::fs::remove_dir_all(directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_skips_the_metadata_too_large_for_quicktime() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let directory = directory("oversized")?;
+        let config = Config {
+            path: directory.join("camera.mov"),
+            ..Default::default()
+        };
+
+        let mut items = items(2);
+        if let Some(Item::Video(packet, _)) = items.first_mut() {
+            packet.spec.metadata = "x".repeat(u16::MAX as usize + 1).as_str().into();
+        }
+
+        mux(&config, items)?;
+        let (timestamps, subtitles) = read(&config.path(1))?;
+
+        assert_eq!(timestamps, [0, 40]);
+        assert_eq!(
+            subtitles,
+            [[&18u16.to_be_bytes()[..], &b"<frame index=\"1\"/>"[..]].concat()]
+        );
+
+        std::fs::remove_dir_all(directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_splits_the_files_when_the_audio_starts_late() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let directory = directory("late")?;
+        let config = Config {
+            path: directory.join("camera.mkv"),
+            ..Default::default()
+        };
+
+        // The audio starts along the 10th frame, once the file was opened with the video only
+        let mut muxer = Muxer::new(config.clone());
+        for (index, item) in items(20).into_iter().enumerate() {
+            match item {
+                Item::Audio(..) if index < 2 * 10 => continue,
+                item if muxer.file.is_none() => {
+                    muxer.pending.push(item);
+                    muxer.flush()?;
+                }
+                item => muxer.write(item)?,
+            }
+        }
+        if let Some(file) = muxer.file.take() {
+            file.close()?;
+        }
+
+        assert_eq!(
+            codecs(&config.path(1))?,
+            [codec::Id::SPEEDHQ, codec::Id::TEXT]
+        );
+
+        // The second file starts at the keyframe following the start of the audio
+        let (timestamps, subtitles) = read(&config.path(2))?;
+        assert_eq!(
+            timestamps,
+            (0..9).map(|index| index * 40).collect::<Vec<_>>()
+        );
+        assert_eq!(subtitles[0], b"<frame index=\"11\"/>");
+
+        std::fs::remove_dir_all(directory)?;
+
+        Ok(())
+    }
+}
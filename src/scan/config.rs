@@ -0,0 +1,42 @@
+use std::net::{IpAddr, SocketAddr};
+
+use super::SourceInfo;
+
+#[cfg(doc)]
+use super::Scan;
+
+/// Configuration for the [`Scan`] structure.
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    /// Groups to watch for sources, only reporting the sources sharing at least one of them,
+    /// defaults to watching all the groups.
+    pub groups: Option<Vec<String>>,
+
+    /// `nndi` discovery server to query for sources instead of using mDNS, see [`crate::discovery`].
+    pub discovery: Option<SocketAddr>,
+
+    /// Extra hosts to probe for sources over unicast mDNS, such as the ones on remote subnets,
+    /// in addition to the other discovery mechanisms.
+    ///
+    /// Only the hosts whose mDNS responder answers _unicast_ queries are reached this way, which
+    /// excludes the `Source`s of this crate, see [`Config::extra_sources`] for these.
+    pub extra_ips: Vec<IpAddr>,
+
+    /// Extra source addresses to probe directly, such as `10.0.0.42:5961`, in addition to the
+    /// other discovery mechanisms, whatever their mDNS responder.
+    ///
+    /// Each address is kept connected as a _sink_ with all it's streams disabled, and the source
+    /// is reported for as long as the connection stays open, in none of the groups,
+    /// as they are not part of the handshake, but whatever the watched [`Config::groups`].
+    pub extra_sources: Vec<SocketAddr>,
+}
+
+impl Config {
+    /// Whether the `source` shares at least one of the watched groups.
+    pub(super) fn accepts(&self, source: &SourceInfo) -> bool {
+        match &self.groups {
+            Some(groups) => source.shares_any(groups),
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! A direct probe of known source addresses, connecting to them as a _sink_ with all it's streams
+//! disabled, and reporting the source answering there for as long as the connection stays open.
+//!
+//! This reaches the sources whatever their mDNS responder, such as the `mdns-sd` one advertising
+//! the `Source`s of this crate, which does not answer _unicast_ queries.
+
+use std::{net::SocketAddr, time::Duration};
+
+use tokio::net::TcpStream;
+
+use crate::{
+    io::Stream,
+    sink::{self, Peer},
+    Result,
+};
+
+use super::{Discovered, Origin, SourceInfo};
+
+/// The delay before connecting to an address again, after the connection failed or was lost.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Keep probing each of the `addresses` for it's source, forwarding them to the `discovered` channel,
+/// until the channel is closed.
+pub async fn probe(addresses: Vec<SocketAddr>, discovered: flume::Sender<Discovered>) {
+    futures::future::join_all(
+        addresses
+            .into_iter()
+            .map(|address| watch(address, discovered.clone())),
+    )
+    .await;
+}
+
+/// Report the source answering at `address` while connected to it, reconnecting when the connection is lost.
+async fn watch(address: SocketAddr, discovered: flume::Sender<Discovered>) {
+    while !discovered.is_disconnected() {
+        match connect(address).await {
+            Ok((source, mut stream)) => {
+                let key = source.to_string();
+                discovered
+                    .send(Discovered::Resolved(Origin::Direct, source))
+                    .ok();
+
+                // The source is alive for as long as the connection is open
+                while stream.recv().await.is_ok() {}
+
+                tracing::debug!("Connection to the probed source `{key}` at `{address}` lost");
+
+                discovered
+                    .send(Discovered::Removed(Origin::Direct, key))
+                    .ok();
+            }
+            Err(err) => tracing::debug!("Unable to probe `{address}` for a source: {err}"),
+        }
+
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+/// Connect to the source at `address`, and handshake with it to retrieve it's name.
+async fn connect(address: SocketAddr) -> Result<(SourceInfo, Stream)> {
+    let config = sink::Config {
+        name: Some("probe"),
+        ..Default::default()
+    };
+
+    tokio::time::timeout(crate::HANDSHAKE_TIMEOUT, async {
+        let mut stream: Stream = TcpStream::connect(address).await?.into();
+        let peer = Peer::handshake(&mut stream, &config).await?;
+
+        // Sources not named after their machine are attributed to their address instead
+        let (machine, name) = SourceInfo::parse_instance(&peer.identify.name)
+            .unwrap_or_else(|| (address.ip().to_string(), peer.identify.name));
+
+        // The groups of the source are not part of the handshake
+        let source = SourceInfo {
+            machine,
+            name,
+            groups: Vec::new(),
+            addresses: vec![address.ip()],
+            port: address.port(),
+        };
+
+        Ok::<_, crate::Error>((source, stream))
+    })
+    .await?
+}
@@ -0,0 +1,377 @@
+//! Everything related to the discovery of NDI sources on the network.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use tokio::task::JoinHandle;
+
+use crate::{discovery, Error, Result};
+
+mod config;
+pub use config::Config;
+
+mod unicast;
+
+mod direct;
+
+mod registry;
+use registry::Registry;
+
+/// The information about an advertised source, either discovered on the network
+/// with [`Scan`], or constructed manually to connect to a known source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceInfo {
+    /// The name of the machine hosting the source.
+    pub machine: String,
+
+    /// The name of the source on it's machine.
+    pub name: String,
+
+    /// The groups the source is advertised in.
+    pub groups: Vec<String>,
+
+    /// The addresses the source can be reached at.
+    pub addresses: Vec<IpAddr>,
+
+    /// The port the source listens on.
+    pub port: u16,
+}
+
+impl SourceInfo {
+    /// Parse the machine and source names from an mDNS _full name_, such as `MACHINE (Source name)._ndi._tcp.local.`.
+    fn parse_name(fullname: &str) -> Option<(String, String)> {
+        Self::parse_instance(
+            fullname
+                .strip_suffix(super::SERVICE_TYPE)?
+                .strip_suffix('.')?,
+        )
+    }
+
+    /// Parse the machine and source names from an _instance_ name, such as `MACHINE (Source name)`.
+    fn parse_instance(instance: &str) -> Option<(String, String)> {
+        let (machine, name) = instance.split_once(" (")?;
+
+        Some((machine.into(), name.strip_suffix(')')?.into()))
+    }
+
+    /// Parse a comma-separated list of groups, discarding the empty entries.
+    pub(crate) fn parse_groups(list: &str) -> Vec<String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+            .map(Into::into)
+            .collect()
+    }
+
+    fn from_service(service: &ServiceInfo) -> Option<Self> {
+        let (machine, name) = Self::parse_name(service.get_fullname())?;
+
+        let groups = service
+            .get_property_val_str("groups")
+            .map(Self::parse_groups)
+            .unwrap_or_default();
+
+        let mut addresses: Vec<_> = service.get_addresses().iter().copied().collect();
+        addresses.sort();
+
+        Some(Self {
+            machine,
+            name,
+            groups,
+            addresses,
+            port: service.get_port(),
+        })
+    }
+
+    /// Whether the source is part of at least one of the provided `groups`, ignoring case.
+    pub(crate) fn shares_any(&self, groups: &[String]) -> bool {
+        self.groups.iter().any(|group| {
+            groups
+                .iter()
+                .any(|other| other.trim().eq_ignore_ascii_case(group.trim()))
+        })
+    }
+
+    /// Whether the provided `name` designates this source, either as `Source name` or `MACHINE (Source name)`.
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.to_string() == name
+    }
+
+    /// The socket addresses the source can be reached at.
+    pub fn socket_addrs(&self) -> Vec<SocketAddr> {
+        self.addresses
+            .iter()
+            .map(|addr| SocketAddr::new(*addr, self.port))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for SourceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.machine, self.name)
+    }
+}
+
+/// A change in the sources advertised on the network.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A new source was discovered.
+    Added(SourceInfo),
+
+    /// A known source changed it's advertisement, such as it's groups, addresses or port.
+    Updated(SourceInfo),
+
+    /// A known source is not advertised anymore, with it's last known information.
+    Removed(SourceInfo),
+}
+
+/// The discovery mechanism which reported a source, in the order their advertisements are preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Origin {
+    /// Multicast mDNS.
+    Mdns,
+
+    /// A discovery server.
+    Discovery,
+
+    /// The unicast mDNS probes of the extra IPs.
+    Unicast,
+
+    /// The direct probes of the extra sources.
+    Direct,
+}
+
+/// A change in the sources advertised by one of the discovery mechanisms, before filtering.
+#[derive(Debug)]
+pub(crate) enum Discovered {
+    /// The source was resolved by the mechanism, either for the first time or again.
+    Resolved(Origin, SourceInfo),
+
+    /// The source designated by it's `MACHINE (Source name)` was removed from the mechanism.
+    Removed(Origin, String),
+}
+
+impl Discovered {
+    fn from_mdns(event: ServiceEvent) -> Option<Self> {
+        match event {
+            ServiceEvent::ServiceResolved(service) => match SourceInfo::from_service(&service) {
+                Some(source) => Some(Self::Resolved(Origin::Mdns, source)),
+                None => {
+                    tracing::debug!("Ignored source with an invalid name: {service:?}");
+
+                    None
+                }
+            },
+            ServiceEvent::ServiceRemoved(_, fullname) => SourceInfo::parse_name(&fullname)
+                .map(|(machine, name)| Self::Removed(Origin::Mdns, format!("{machine} ({name})"))),
+            _ => None,
+        }
+    }
+}
+
+/// A source scanner, providing an iterator of advertised _sources_ over mDNS,
+/// or from a _discovery server_, and from the probed _extra IPs_ and _extra sources_.
+pub struct Scan {
+    config: Config,
+    mdns: Option<(ServiceDaemon, mdns_sd::Receiver<ServiceEvent>)>,
+    tasks: Vec<JoinHandle<()>>,
+    discovered: flume::Receiver<Discovered>,
+    registry: Registry,
+}
+
+impl Scan {
+    /// Create a new source scanner over the network, based on the provided `config`.
+    ///
+    /// When [`Config::discovery`], [`Config::extra_ips`] or [`Config::extra_sources`] are set,
+    /// this must be called from within a `tokio` runtime.
+    pub fn new(config: Config) -> Result<Self> {
+        let (sender, discovered) = flume::unbounded();
+        let mut tasks = Vec::new();
+
+        let mdns = match config.discovery {
+            Some(server) => {
+                tasks.push(tokio::spawn(discovery::client::subscribe(
+                    server,
+                    config.groups.clone().unwrap_or_default(),
+                    sender.clone(),
+                )));
+
+                None
+            }
+            None => {
+                let mdns = ServiceDaemon::new()?;
+                let receiver = mdns.browse(super::SERVICE_TYPE)?;
+
+                Some((mdns, receiver))
+            }
+        };
+
+        if !config.extra_ips.is_empty() {
+            tasks.push(tokio::spawn(unicast::probe(
+                config.extra_ips.clone(),
+                sender.clone(),
+            )));
+        }
+
+        if !config.extra_sources.is_empty() {
+            tasks.push(tokio::spawn(direct::probe(
+                config.extra_sources.clone(),
+                sender,
+            )));
+        }
+
+        Ok(Self {
+            config,
+            mdns,
+            tasks,
+            discovered,
+            registry: Default::default(),
+        })
+    }
+
+    /// Retrieve the next discovered change without waiting, if any.
+    fn try_recv(&self) -> Option<Discovered> {
+        if let Some((_, receiver)) = &self.mdns {
+            while let Ok(event) = receiver.try_recv() {
+                if let Some(discovered) = Discovered::from_mdns(event) {
+                    return Some(discovered);
+                }
+            }
+        }
+
+        self.discovered.try_recv().ok()
+    }
+
+    /// Wait for the next discovered change, or `None` if the discovery stopped.
+    async fn recv(&self) -> Option<Discovered> {
+        loop {
+            let mdns = async {
+                match &self.mdns {
+                    Some((_, receiver)) => receiver.recv_async().await.ok(),
+                    None => None,
+                }
+            };
+
+            tokio::select! {
+                Some(event) = mdns => {
+                    if let Some(discovered) = Discovered::from_mdns(event) {
+                        break Some(discovered);
+                    }
+                }
+                Ok(discovered) = self.discovered.recv_async() => break Some(discovered),
+                else => break None,
+            }
+        }
+    }
+
+    /// Apply the `discovered` change to the known sources, and convert it to an [`Event`] if relevant.
+    fn handle(&mut self, discovered: Discovered) -> Option<Event> {
+        match discovered {
+            Discovered::Resolved(origin, source) => {
+                let key = source.to_string();
+
+                // Sources leaving the watched groups are reported as removed, while the groups
+                // of the extra sources are unknown, as they were explicitly requested
+                if origin != Origin::Direct && !self.config.accepts(&source) {
+                    return self.registry.report(key, origin, None);
+                }
+
+                self.registry.report(key, origin, Some(source))
+            }
+            Discovered::Removed(origin, key) => self.registry.report(key, origin, None),
+        }
+    }
+
+    /// Iterate over the retrieved _sources_.
+    pub fn sources(&mut self) -> impl Iterator<Item = &SourceInfo> {
+        while let Some(discovered) = self.try_recv() {
+            self.handle(discovered);
+        }
+
+        self.registry.sources()
+    }
+
+    /// Stream the changes in the advertised _sources_, as they happen.
+    ///
+    /// Sources already retrieved by a previous call to [`Scan::sources`] or [`Scan::events`]
+    /// are not yielded again, unless their advertisement changes.
+    pub fn events(&mut self) -> impl futures::Stream<Item = Event> + '_ {
+        futures::stream::unfold(self, |scan| async move {
+            loop {
+                let discovered = scan.recv().await?;
+
+                if let Some(event) = scan.handle(discovered) {
+                    break Some((event, scan));
+                }
+            }
+        })
+    }
+
+    /// Wait for the source designated by `name`, either as `Source name` or `MACHINE (Source name)`,
+    /// to be advertised, for at most `timeout`.
+    pub async fn wait_for(&mut self, name: &str, timeout: Duration) -> Result<SourceInfo> {
+        if let Some(source) = self.sources().find(|source| source.matches(name)) {
+            return Ok(source.clone());
+        }
+
+        tokio::time::timeout(timeout, async {
+            let mut events = std::pin::pin!(self.events());
+
+            while let Some(event) = events.next().await {
+                match event {
+                    Event::Added(source) | Event::Updated(source) if source.matches(name) => {
+                        return Ok(source);
+                    }
+                    _ => (),
+                }
+            }
+
+            Err(Error::ClosedChannel)
+        })
+        .await?
+    }
+}
+
+impl Drop for Scan {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+
+        if let Some((mdns, _)) = &self.mdns {
+            if let Err(err) = mdns.stop_browse(super::SERVICE_TYPE) {
+                tracing::error!(
+                    "Error while stopping the mDNS discovery of {}: {err}",
+                    super::SERVICE_TYPE
+                );
+            }
+
+            if let Err(err) = mdns.shutdown() {
+                tracing::error!("Error while shutting down the mDNS discovery thread: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_source_names() {
+        assert_eq!(
+            SourceInfo::parse_name("STUDIO-PC (Camera 1 (wide))._ndi._tcp.local."),
+            Some(("STUDIO-PC".into(), "Camera 1 (wide)".into()))
+        );
+
+        assert_eq!(SourceInfo::parse_name("STUDIO-PC._ndi._tcp.local."), None);
+        assert_eq!(
+            SourceInfo::parse_name("STUDIO-PC (Camera 1)._http._tcp.local."),
+            None
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::{Event, Origin, SourceInfo};
+
+/// The sources known to the `Scan`, merged from the reports of each of the discovery mechanisms.
+#[derive(Debug, Default)]
+pub(super) struct Registry {
+    /// The merged view of the sources, by `MACHINE (Source name)`.
+    sources: HashMap<String, SourceInfo>,
+
+    /// The last report of each mechanism seeing the sources, by `MACHINE (Source name)`.
+    reports: HashMap<String, BTreeMap<Origin, SourceInfo>>,
+}
+
+impl Registry {
+    /// Iterate over the merged view of the sources.
+    pub fn sources(&self) -> impl Iterator<Item = &SourceInfo> {
+        self.sources.values()
+    }
+
+    /// Record the `source` designated by `key` as seen by the `origin` mechanism, or lost by it when `None`,
+    /// and convert it to an [`Event`] if the merged view of the source changed.
+    ///
+    /// A source is only removed once all the mechanisms which reported it lost it.
+    pub fn report(
+        &mut self,
+        key: String,
+        origin: Origin,
+        source: Option<SourceInfo>,
+    ) -> Option<Event> {
+        let reports = self.reports.entry(key.clone()).or_default();
+        match source {
+            Some(source) => reports.insert(origin, source),
+            None => reports.remove(&origin),
+        };
+
+        let merged = Self::merge(reports);
+        if reports.is_empty() {
+            self.reports.remove(&key);
+        }
+
+        let previous = match &merged {
+            Some(merged) => self.sources.insert(key, merged.clone()),
+            None => self.sources.remove(&key),
+        };
+
+        match (previous, merged) {
+            (None, Some(merged)) => Some(Event::Added(merged)),
+            // Sources are reported again on each refresh, only report actual changes
+            (Some(previous), Some(merged)) if previous != merged => Some(Event::Updated(merged)),
+            (Some(previous), None) => Some(Event::Removed(previous)),
+            _ => None,
+        }
+    }
+
+    /// Merge the `reports` of a source, taking the advertisement of the first mechanism
+    /// in the [`Origin`] order, and the addresses of all of them.
+    fn merge(reports: &BTreeMap<Origin, SourceInfo>) -> Option<SourceInfo> {
+        let mut merged = reports.values().next()?.clone();
+        merged.addresses = reports
+            .values()
+            .flat_map(|report| report.addresses.iter().copied())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        Some(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+
+    fn source(address: [u8; 4]) -> SourceInfo {
+        SourceInfo {
+            machine: "STUDIO-PC".into(),
+            name: "Camera 1".into(),
+            groups: vec!["public".into()],
+            addresses: vec![IpAddr::V4(Ipv4Addr::from(address))],
+            port: 5961,
+        }
+    }
+
+    #[test]
+    fn it_merges_the_reports_of_each_mechanism() {
+        let mut registry = Registry::default();
+        let key = source([10, 0, 0, 1]).to_string();
+
+        let multicast = source([10, 0, 0, 1]);
+        let unicast = source([192, 168, 1, 1]);
+        let merged = SourceInfo {
+            addresses: [multicast.addresses.clone(), unicast.addresses.clone()].concat(),
+            ..multicast.clone()
+        };
+
+        assert!(matches!(
+            registry.report(key.clone(), Origin::Mdns, Some(multicast.clone())),
+            Some(Event::Added(source)) if source == multicast
+        ));
+        assert!(matches!(
+            registry.report(key.clone(), Origin::Unicast, Some(unicast.clone())),
+            Some(Event::Updated(source)) if source == merged
+        ));
+
+        // Refreshes of the same information by either mechanism are not reported
+        for _ in 0..3 {
+            assert!(registry
+                .report(key.clone(), Origin::Mdns, Some(multicast.clone()))
+                .is_none());
+            assert!(registry
+                .report(key.clone(), Origin::Unicast, Some(unicast.clone()))
+                .is_none());
+        }
+        assert_eq!(registry.sources().collect::<Vec<_>>(), [&merged]);
+    }
+
+    #[test]
+    fn it_removes_sources_lost_by_every_mechanism() {
+        let mut registry = Registry::default();
+        let key = source([10, 0, 0, 1]).to_string();
+
+        let multicast = source([10, 0, 0, 1]);
+        let unicast = source([192, 168, 1, 1]);
+
+        registry.report(key.clone(), Origin::Mdns, Some(multicast.clone()));
+        registry.report(key.clone(), Origin::Unicast, Some(unicast.clone()));
+
+        assert!(matches!(
+            registry.report(key.clone(), Origin::Unicast, None),
+            Some(Event::Updated(source)) if source == multicast
+        ));
+        assert!(registry
+            .report(key.clone(), Origin::Unicast, None)
+            .is_none());
+        assert!(matches!(
+            registry.report(key.clone(), Origin::Mdns, None),
+            Some(Event::Removed(source)) if source == multicast
+        ));
+        assert_eq!(registry.sources().count(), 0);
+    }
+}
@@ -0,0 +1,380 @@
+//! A minimal _unicast_ mDNS client, to probe hosts out of reach of multicast for their sources,
+//! using _legacy unicast_ queries, to which responders answer directly (see RFC 6762, section 6.7).
+//!
+//! Responders which only answer over multicast, such as the `mdns-sd` one advertising the
+//! `Source`s of this crate, are not reached this way, and are probed directly instead.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use tokio::net::UdpSocket;
+
+use crate::Result;
+
+use super::{Discovered, Origin, SourceInfo};
+
+/// The port mDNS responders listen on.
+const MDNS_PORT: u16 = 5353;
+
+/// The interval between two probes of the hosts.
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The time to wait for the responses of a host after querying it.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The time after which a source which was not seen anymore is considered removed.
+const EXPIRY: Duration = Duration::from_secs(15);
+
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+/// Periodically probe the `hosts` for their sources, forwarding them to the `discovered` channel,
+/// until the channel is closed.
+pub async fn probe(hosts: Vec<IpAddr>, discovered: flume::Sender<Discovered>) {
+    let mut seen = HashMap::<String, Instant>::new();
+    let mut interval = tokio::time::interval(PROBE_INTERVAL);
+
+    while !discovered.is_disconnected() {
+        interval.tick().await;
+
+        let results = futures::future::join_all(hosts.iter().map(|host| query(*host))).await;
+
+        // Merge the sources answered by several of the hosts, so their addresses do not alternate
+        let mut round = HashMap::<String, SourceInfo>::new();
+        for (host, result) in hosts.iter().zip(results) {
+            match result {
+                Ok(sources) => {
+                    for source in sources {
+                        match round.entry(source.to_string()) {
+                            Entry::Occupied(mut entry) => {
+                                entry.get_mut().addresses.extend(source.addresses)
+                            }
+                            Entry::Vacant(entry) => {
+                                entry.insert(source);
+                            }
+                        }
+                    }
+                }
+                Err(err) => tracing::debug!("Unable to probe `{host}` over unicast mDNS: {err}"),
+            }
+        }
+
+        for (key, mut source) in round {
+            source.addresses.sort();
+            source.addresses.dedup();
+
+            seen.insert(key, Instant::now());
+            discovered
+                .send(Discovered::Resolved(Origin::Unicast, source))
+                .ok();
+        }
+
+        // Sources which were not seen for a while are considered gone from the probed hosts,
+        // the other mechanisms may still see them
+        seen.retain(|key, last| {
+            let alive = last.elapsed() < EXPIRY;
+            if !alive {
+                discovered
+                    .send(Discovered::Removed(Origin::Unicast, key.clone()))
+                    .ok();
+            }
+
+            alive
+        });
+    }
+}
+
+/// Query the `host` for it's sources over unicast mDNS.
+async fn query(host: IpAddr) -> Result<Vec<SourceInfo>> {
+    let local: SocketAddr = match host {
+        IpAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        IpAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect((host, MDNS_PORT)).await?;
+    socket.send(&request()).await?;
+
+    let mut records = Vec::new();
+    let mut buf = vec![0; 9000];
+    let deadline = tokio::time::Instant::now() + RESPONSE_TIMEOUT;
+
+    while let Ok(Ok(len)) = tokio::time::timeout_at(deadline, socket.recv(&mut buf)).await {
+        match parse(&buf[..len]) {
+            Some(mut parsed) => records.append(&mut parsed),
+            None => tracing::debug!("Discarded a malformed mDNS response from `{host}`"),
+        }
+    }
+
+    Ok(resolve(host, &records))
+}
+
+/// Build a query for the instances of the NDI service.
+fn request() -> Vec<u8> {
+    // Identifier, flags, and a single question
+    let mut packet = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+
+    for label in crate::SERVICE_TYPE
+        .split('.')
+        .filter(|label| !label.is_empty())
+    {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+
+    packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+    packet
+}
+
+/// The relevant data of a resource record.
+#[derive(Debug, PartialEq)]
+enum Data {
+    Ptr(String),
+    Srv { port: u16 },
+    Txt(Vec<String>),
+    Other,
+}
+
+/// A resource record from an mDNS response.
+#[derive(Debug, PartialEq)]
+struct Record {
+    name: String,
+    data: Data,
+}
+
+/// Assemble the sources of the `host` from the provided `records`.
+fn resolve(host: IpAddr, records: &[Record]) -> Vec<SourceInfo> {
+    fn find<'r>(records: &'r [Record], instance: &'r str) -> impl Iterator<Item = &'r Record> {
+        records
+            .iter()
+            .filter(move |record| record.name.eq_ignore_ascii_case(instance))
+    }
+
+    records
+        .iter()
+        .filter(|record| record.name.eq_ignore_ascii_case(crate::SERVICE_TYPE))
+        .filter_map(|record| match &record.data {
+            Data::Ptr(instance) => Some(instance),
+            _ => None,
+        })
+        .filter_map(|instance| {
+            let (machine, name) = SourceInfo::parse_name(instance)?;
+            let port = find(records, instance).find_map(|record| match record.data {
+                Data::Srv { port } => Some(port),
+                _ => None,
+            })?;
+            let groups = find(records, instance)
+                .filter_map(|record| match &record.data {
+                    Data::Txt(entries) => Some(entries),
+                    _ => None,
+                })
+                .flatten()
+                .find_map(|entry| entry.strip_prefix("groups="))
+                .map(SourceInfo::parse_groups)
+                .unwrap_or_default();
+
+            // The source is reachable at the probed address, whatever the addresses it advertises
+            Some(SourceInfo {
+                machine,
+                name,
+                groups,
+                addresses: vec![host],
+                port,
+            })
+        })
+        .collect()
+}
+
+/// A reader over an mDNS packet.
+struct Parser<'p> {
+    packet: &'p [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn bytes(&mut self, len: usize) -> Option<&[u8]> {
+        let bytes = self.packet.get(self.pos..self.pos + len)?;
+        self.pos += len;
+
+        Some(bytes)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    /// Read a domain name, following the compression pointers.
+    fn name(&mut self) -> Option<String> {
+        let mut name = String::new();
+        let mut pos = self.pos;
+        let mut jumps = 0;
+
+        loop {
+            let len = *self.packet.get(pos)? as usize;
+
+            match len {
+                0 => {
+                    pos += 1;
+
+                    break;
+                }
+                len if len & 0xc0 == 0xc0 => {
+                    let pointer = ((len & 0x3f) << 8) | *self.packet.get(pos + 1)? as usize;
+
+                    // Resume after the first pointer, and guard against pointer loops
+                    if jumps == 0 {
+                        self.pos = pos + 2;
+                    }
+                    jumps += 1;
+                    if jumps > 16 {
+                        return None;
+                    }
+
+                    pos = pointer;
+                }
+                len => {
+                    let label = self.packet.get(pos + 1..pos + 1 + len)?;
+                    name.push_str(&String::from_utf8_lossy(label));
+                    name.push('.');
+
+                    pos += 1 + len;
+                }
+            }
+        }
+
+        if jumps == 0 {
+            self.pos = pos;
+        }
+
+        Some(name)
+    }
+
+    fn record(&mut self) -> Option<Record> {
+        let name = self.name()?;
+        let kind = self.u16()?;
+        let _class = self.u16()?;
+        let _ttl = self.u32()?;
+        let len = self.u16()? as usize;
+
+        let end = self.pos + len;
+        if end > self.packet.len() {
+            return None;
+        }
+
+        let data = match kind {
+            TYPE_PTR => Data::Ptr(self.name()?),
+            TYPE_SRV => {
+                let _priority = self.u16()?;
+                let _weight = self.u16()?;
+
+                Data::Srv { port: self.u16()? }
+            }
+            TYPE_TXT => {
+                let mut entries = Vec::new();
+                while self.pos < end {
+                    let len = *self.bytes(1)?.first()? as usize;
+                    entries.push(String::from_utf8_lossy(self.bytes(len)?).into_owned());
+                }
+
+                Data::Txt(entries)
+            }
+            _ => Data::Other,
+        };
+        self.pos = end;
+
+        Some(Record { name, data })
+    }
+}
+
+/// Parse the resource records of an mDNS response `packet`.
+fn parse(packet: &[u8]) -> Option<Vec<Record>> {
+    let mut parser = Parser { packet, pos: 0 };
+
+    let _id = parser.u16()?;
+    let flags = parser.u16()?;
+    let questions = parser.u16()?;
+    let records = parser.u16()? as usize + parser.u16()? as usize + parser.u16()? as usize;
+
+    // Only consider responses
+    if flags & 0x8000 == 0 {
+        return None;
+    }
+
+    for _ in 0..questions {
+        parser.name()?;
+        parser.bytes(4)?;
+    }
+
+    (0..records).map(|_| parser.record()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(packet: &mut Vec<u8>, name: &str) {
+        for label in name.split('.').filter(|label| !label.is_empty()) {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+    }
+
+    fn record(packet: &mut Vec<u8>, owner: impl Fn(&mut Vec<u8>), kind: u16, data: &[u8]) {
+        owner(packet);
+        packet.extend_from_slice(&kind.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet.extend_from_slice(&120u32.to_be_bytes());
+        packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        packet.extend_from_slice(data);
+    }
+
+    #[test]
+    fn it_resolves_sources_from_a_response() {
+        let instance = "STUDIO-PC (Camera 1)._ndi._tcp.local.";
+
+        let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 2];
+
+        // The PTR record owner is at offset 12, so it's target is referenced with a pointer
+        let mut ptr = Vec::new();
+        name(&mut ptr, "STUDIO-PC (Camera 1)");
+        ptr.truncate(ptr.len() - 1);
+        ptr.extend_from_slice(&[0xc0, 12]);
+        record(&mut packet, |p| name(p, "_ndi._tcp.local."), TYPE_PTR, &ptr);
+
+        let mut srv = vec![0, 0, 0, 0];
+        srv.extend_from_slice(&5961u16.to_be_bytes());
+        name(&mut srv, "studio-pc.local.");
+        record(&mut packet, |p| name(p, instance), TYPE_SRV, &srv);
+
+        let txt = b"\x0dgroups=public";
+        record(&mut packet, |p| name(p, instance), TYPE_TXT, txt);
+
+        let records = parse(&packet).expect("Unable to parse the response");
+        assert_eq!(records[0].data, Data::Ptr(instance.into()));
+
+        let host = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 42));
+        assert_eq!(
+            resolve(host, &records),
+            [SourceInfo {
+                machine: "STUDIO-PC".into(),
+                name: "Camera 1".into(),
+                groups: vec!["public".into()],
+                addresses: vec![host],
+                port: 5961,
+            }]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::str::FromStr;
+
+use crate::Error;
+
+/// The direct address of a source, to connect to it without discovery,
+/// in the form of `ndi://host:port/name`, where the scheme and name are optional.
+///
+/// IPv6 hosts must be enclosed in brackets, such as `ndi://[::1]:5961/`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    /// The hostname or IP address of the source.
+    pub host: String,
+
+    /// The port the source listens on.
+    pub port: u16,
+
+    /// The name of the source, if specified, either as `Source name` or `MACHINE (Source name)`,
+    /// checked against the source reached by [`super::Sink::connect_to`].
+    pub name: Option<String>,
+}
+
+impl Address {
+    /// The `(host, port)` pair of the source, to be passed to [`super::Sink::connect`].
+    pub fn socket_addr(&self) -> (&str, u16) {
+        (&self.host, self.port)
+    }
+}
+
+/// Whether the source `name`, either as `Source name` or `MACHINE (Source name)`,
+/// designates the source identified as `identify`.
+pub(super) fn designates(name: &str, identify: &str) -> bool {
+    identify == name
+        || identify
+            .strip_suffix(')')
+            .and_then(|identify| identify.split_once(" ("))
+            .is_some_and(|(_, source)| source == name)
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidAddress(s.into());
+
+        let address = s.strip_prefix("ndi://").unwrap_or(s);
+        let (authority, name) = match address.split_once('/') {
+            Some((authority, name)) => (authority, Some(name).filter(|name| !name.is_empty())),
+            None => (address, None),
+        };
+
+        let (host, port) = authority.rsplit_once(':').ok_or_else(invalid)?;
+        let host = match host.strip_prefix('[') {
+            Some(host) => host.strip_suffix(']').ok_or_else(invalid)?,
+            // Unbracketed IPv6 addresses are ambiguous with the port separator
+            None if host.contains(':') => return Err(invalid()),
+            None => host,
+        };
+
+        if host.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            host: host.into(),
+            port: port.parse().map_err(|_| invalid())?,
+            name: name.map(Into::into),
+        })
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "ndi://[{}]:{}/", self.host, self.port)?;
+        } else {
+            write!(f, "ndi://{}:{}/", self.host, self.port)?;
+        }
+
+        match &self.name {
+            Some(name) => write!(f, "{name}"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_addresses() -> Result<(), Error> {
+        assert_eq!(
+            "ndi://studio-pc:5961/Camera 1".parse::<Address>()?,
+            Address {
+                host: "studio-pc".into(),
+                port: 5961,
+                name: Some("Camera 1".into()),
+            }
+        );
+        assert_eq!(
+            "[fe80::1]:5961".parse::<Address>()?,
+            Address {
+                host: "fe80::1".into(),
+                port: 5961,
+                name: None,
+            }
+        );
+        assert_eq!(
+            "ndi://[fe80::1]:5961/Camera 1"
+                .parse::<Address>()?
+                .to_string(),
+            "ndi://[fe80::1]:5961/Camera 1"
+        );
+
+        assert!("ndi://studio-pc/Camera 1".parse::<Address>().is_err());
+        assert!("ndi://studio-pc:ndi/".parse::<Address>().is_err());
+        assert!("ndi://:5961/".parse::<Address>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_requires_brackets_around_ipv6() {
+        assert!("ndi://::1:5961".parse::<Address>().is_err());
+        assert!("fe80::1:5961/Camera 1".parse::<Address>().is_err());
+        assert!("ndi://[::1:5961".parse::<Address>().is_err());
+        assert!("ndi://[::1]:5961".parse::<Address>().is_ok());
+    }
+
+    #[test]
+    fn it_designates_sources_by_name() {
+        assert!(designates("Camera 1", "STUDIO-PC (Camera 1)"));
+        assert!(designates("STUDIO-PC (Camera 1)", "STUDIO-PC (Camera 1)"));
+        assert!(designates("Camera 1 (wide)", "STUDIO-PC (Camera 1 (wide))"));
+
+        assert!(!designates("Camera 2", "STUDIO-PC (Camera 1)"));
+        assert!(!designates("STUDIO-PC", "STUDIO-PC (Camera 1)"));
+    }
+}
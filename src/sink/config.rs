@@ -1,7 +1,11 @@
-use crate::io::frame::text;
+use std::time::Duration;
 
+use crate::{io::frame::text, scan};
+
+#[cfg(doc)]
+use super::{Event, Sink};
 #[cfg(doc)]
-use super::Sink;
+use crate::Scan;
 
 /// Configuration for the [`Sink`] structure.
 #[derive(Debug, Default, Clone)]
@@ -15,6 +19,55 @@ pub struct Config<'s> {
     /// Size of the [`ffmpeg::frame::Audio`] queue to be retained until incoming frames are dropped. Set to `0` to disable audio streaming.
     pub audio_queue: usize,
 
+    /// Size of the [`text::Message`] queue to be retained until incoming metadata are dropped. Set to `0` to discard metadata.
+    pub metadata_queue: usize,
+
     /// Quality of the video stream to request to the source.
     pub video_quality: text::VideoQuality,
+
+    /// Policy to reconnect to the source when the connection is lost, disabled by default.
+    pub reconnect: Option<Reconnect>,
+}
+
+impl Config<'_> {
+    /// Copy the configuration without the borrowed sink `name`, to hand it to the task.
+    pub(super) fn detached(&self) -> Config<'static> {
+        Config {
+            name: None,
+            video_queue: self.video_queue,
+            audio_queue: self.audio_queue,
+            metadata_queue: self.metadata_queue,
+            video_quality: self.video_quality.clone(),
+            reconnect: self.reconnect.clone(),
+        }
+    }
+}
+
+/// Policy applied to reconnect a [`Sink`] to it's source when the connection is lost,
+/// reported through [`Event`]s.
+#[derive(Debug, Clone)]
+pub struct Reconnect {
+    /// Delay before the first reconnection attempt, doubled after each failed one, defaults to `500ms`.
+    pub backoff: Duration,
+
+    /// Maximum delay between two reconnection attempts, defaults to `10s`.
+    pub max_backoff: Duration,
+
+    /// Maximum number of consecutive failed attempts before giving up, defaults to retrying forever.
+    pub max_attempts: Option<usize>,
+
+    /// Re-resolve the source by name with a [`Scan`] using this configuration before each attempt,
+    /// instead of reusing the original address, to follow a source which moved.
+    pub rescan: Option<scan::Config>,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            max_attempts: None,
+            rescan: None,
+        }
+    }
 }
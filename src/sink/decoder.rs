@@ -0,0 +1,185 @@
+use ffmpeg::codec;
+
+use crate::{
+    io::frame::{audio, video},
+    Result,
+};
+
+/// The stream parameters a [`VideoDecoder`] was opened with.
+#[derive(PartialEq)]
+struct VideoParams {
+    fourcc: video::FourCCVideoType,
+    width: u32,
+    height: u32,
+    fps_num: u32,
+    fps_den: u32,
+}
+
+struct VideoContext {
+    params: VideoParams,
+    extradata: Vec<u8>,
+    decoder: codec::decoder::Video,
+}
+
+/// A long-lived video decoder, only re-created when the stream parameters change.
+///
+/// The decoder state is kept across blocks, as required by inter-frame codecs such as
+/// `H264` and `HEVC`, whose blocks are skipped until the first keyframe is received.
+#[derive(Default)]
+pub struct VideoDecoder {
+    context: Option<VideoContext>,
+}
+
+impl VideoDecoder {
+    fn open(params: VideoParams, extradata: &[u8]) -> Result<VideoContext> {
+        let mut context = codec::Context::new();
+        // SAFETY: The pointer is allocated on the line before,
+        // and is guaranteed to be exclusive with `as_mut_ptr`.
+        unsafe {
+            (*context.as_mut_ptr()).codec_tag = params.fourcc.to_code();
+            (*context.as_mut_ptr()).width = params.fourcc.coded_width(params.width) as i32;
+            (*context.as_mut_ptr()).height = params.height as i32;
+            (*context.as_mut_ptr()).framerate = ffmpeg::ffi::AVRational {
+                num: params.fps_num as i32,
+                den: params.fps_den as i32,
+            };
+        }
+
+        if !extradata.is_empty() {
+            // SAFETY: The buffer is allocated with the padding required by `libavcodec`, which
+            // takes ownership of it and frees it along with the context.
+            unsafe {
+                let buffer = ffmpeg::ffi::av_mallocz(
+                    extradata.len() + ffmpeg::ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize,
+                ) as *mut u8;
+                if buffer.is_null() {
+                    return Err(ffmpeg::Error::Other {
+                        errno: ffmpeg::error::ENOMEM,
+                    }
+                    .into());
+                }
+
+                std::ptr::copy_nonoverlapping(extradata.as_ptr(), buffer, extradata.len());
+                (*context.as_mut_ptr()).extradata = buffer;
+                (*context.as_mut_ptr()).extradata_size = extradata.len() as i32;
+            }
+        }
+
+        let decoder = context
+            .decoder()
+            .open_as(codec::decoder::find(params.fourcc.to_codec()))?
+            .video()?;
+
+        tracing::debug!(
+            "Opened a new `{}` decoder for {}x{}@{}/{}",
+            params.fourcc.as_ref(),
+            params.width,
+            params.height,
+            params.fps_num,
+            params.fps_den
+        );
+
+        Ok(VideoContext {
+            params,
+            extradata: extradata.to_vec(),
+            decoder,
+        })
+    }
+
+    /// Decode a [`video::Block`] to the [`ffmpeg::frame::Video`]s it contains.
+    pub fn decode(&mut self, block: &video::Block) -> Result<Vec<ffmpeg::frame::Video>> {
+        let params = VideoParams {
+            fourcc: block.header.fourcc,
+            width: block.header.width,
+            height: block.header.height,
+            fps_num: block.header.fps_num,
+            fps_den: block.header.fps_den,
+        };
+
+        let context = if params.fourcc.is_inter() {
+            let packet = video::Packet::from_bytes(&block.data)?;
+
+            let context = match &mut self.context {
+                Some(context)
+                    if context.params == params
+                        && (packet.extradata.is_empty()
+                            || packet.extradata == context.extradata) =>
+                {
+                    context
+                }
+                // Only start decoding on a keyframe, as the other ones reference previous frames.
+                context if packet.keyframe => {
+                    context.insert(Self::open(params, &packet.extradata)?)
+                }
+                context => {
+                    tracing::trace!(
+                        "Skipped a `{}` block while awaiting a keyframe",
+                        params.fourcc.as_ref()
+                    );
+
+                    *context = None;
+                    return Ok(Vec::new());
+                }
+            };
+
+            let mut data = codec::packet::Packet::copy(&packet.data);
+            data.set_pts(Some(packet.pts));
+            data.set_dts(Some(packet.dts));
+            context.decoder.send_packet(&data)?;
+
+            context
+        } else {
+            let context = match &mut self.context {
+                Some(context) if context.params == params => context,
+                context => context.insert(Self::open(params, &[])?),
+            };
+
+            context
+                .decoder
+                .send_packet(&codec::packet::Packet::borrow(&block.data))?;
+
+            context
+        };
+
+        let width = context.params.width;
+        Ok(std::iter::from_fn(|| {
+            let mut frame = ffmpeg::frame::Video::empty();
+            context.decoder.receive_frame(&mut frame).ok()?;
+
+            // Crop the padding of frames coded wider than the picture, such as SpeedHQ ones.
+            if frame.width() > width {
+                // SAFETY: The frame was just received and is exclusively owned here,
+                // and narrowing it only leaves the padding columns of each row unread.
+                unsafe {
+                    (*frame.as_mut_ptr()).width = width as i32;
+                }
+            }
+
+            Some(frame)
+        })
+        .collect())
+    }
+}
+
+/// Unpack an [`audio::Block`] to the [`ffmpeg::frame::Audio`] it contains.
+pub fn audio(block: &audio::Block) -> Result<ffmpeg::frame::Audio> {
+    let mut frame = ffmpeg::frame::Audio::new(
+        block.header.fourcc.to_format(),
+        block.header.samples as usize,
+        ffmpeg::ChannelLayout::default(block.header.num_channels as i32),
+    );
+    frame.set_rate(block.header.sample_rate);
+
+    let planes = frame.planes();
+    let len = audio::plane_len(&frame, block.header.num_channels as usize);
+
+    if planes == 0 || block.data.len() != len * planes {
+        return Err(ffmpeg::Error::InvalidData.into());
+    }
+
+    for (plane, data) in block.data.chunks_exact(len).enumerate() {
+        frame.data_mut(plane)[..len].copy_from_slice(data);
+    }
+
+    Ok(frame)
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    io::frame::{audio, text, video},
+    Error,
+};
+
+use super::Peer;
+
+/// An event related to the connection of the `Sink` to it's source.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The connection to the source was lost, reconnection attempts follow.
+    Disconnected(Arc<Error>),
+
+    /// A reconnection attempt started, numbered from `1`.
+    Reconnecting {
+        /// The number of the attempt since the connection was lost.
+        attempt: usize,
+    },
+
+    /// The connection to the source was restored, and frames are delivered again,
+    /// with the [`Peer`] of the new handshake, also reflected by `Sink::peer`.
+    Reconnected(Peer),
+
+    /// The source could not be reached within the configured attempts, the `Sink` stopped.
+    Lost,
+}
+
+/// The senders used by the task to deliver incoming data to the `Sink`.
+pub(super) struct Senders {
+    pub peer: watch::Sender<Peer>,
+    pub video: flume::Sender<video::Block>,
+    pub audio: flume::Sender<audio::Block>,
+    pub metadata: flume::Sender<text::Message>,
+    pub tally: watch::Sender<Option<text::Tally>>,
+    pub events: broadcast::Sender<Event>,
+}
@@ -0,0 +1,130 @@
+use std::{net::SocketAddr, time::Duration};
+
+use tokio::{net::TcpStream, sync::broadcast};
+
+use crate::{
+    io::{frame::Frame, Stream},
+    Error, Result, Scan,
+};
+
+use super::{address, Config, Event, Peer, Reconnect};
+
+/// The time allowed to the [`Scan`] to find the source again on each reconnection attempt.
+const RESCAN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Everything needed to establish the connection to the source again.
+pub(super) struct Link {
+    /// The name of the source, as `MACHINE (Source name)`.
+    pub source: String,
+
+    /// The name of the sink, advertised to the source.
+    pub name: Option<String>,
+
+    /// The last known addresses of the source.
+    pub targets: Vec<SocketAddr>,
+
+    /// The name the source is expected to identify with, if any.
+    pub expected: Option<String>,
+
+    pub config: Config<'static>,
+}
+
+impl Link {
+    /// Connect to the source and complete the handshake.
+    pub async fn connect(&self) -> Result<(Stream, Peer)> {
+        let mut stream: Stream = TcpStream::connect(self.targets.as_slice()).await?.into();
+
+        let config = Config {
+            name: self.name.as_deref(),
+            ..self.config.clone()
+        };
+        let peer = tokio::time::timeout(
+            crate::HANDSHAKE_TIMEOUT,
+            Peer::handshake(&mut stream, &config),
+        )
+        .await??;
+
+        if let Some(expected) = &self.expected {
+            if !address::designates(expected, &peer.identify.name) {
+                return Err(Error::UnexpectedSource {
+                    expected: expected.clone(),
+                    found: peer.identify.name,
+                });
+            }
+        }
+
+        Ok((stream, peer))
+    }
+
+    /// Attempt to reconnect to the source according to the `policy`, until it succeeds,
+    /// the attempts are exhausted, or all the `Sink` handles were dropped.
+    ///
+    /// The new [`Peer`] is returned along with the stream, for the caller to announce it.
+    pub async fn reconnect(
+        &mut self,
+        policy: &Reconnect,
+        events: &broadcast::Sender<Event>,
+        outgoing: &flume::Receiver<Frame>,
+    ) -> Result<Option<(Stream, Peer)>> {
+        let mut scan = None;
+        let mut delay = policy.backoff;
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+
+            tokio::time::sleep(delay).await;
+            delay = delay.saturating_mul(2).min(policy.max_backoff);
+
+            if outgoing.is_disconnected() {
+                tracing::debug!(
+                    "All handles dropped, stopped reconnecting to `{}`",
+                    self.source
+                );
+
+                return Ok(None);
+            }
+
+            events.send(Event::Reconnecting { attempt }).ok();
+
+            match self.attempt(policy, &mut scan).await {
+                Ok(connected) => {
+                    tracing::info!(
+                        "Reconnected to `{}` after {attempt} attempt(s)",
+                        self.source
+                    );
+
+                    return Ok(Some(connected));
+                }
+                Err(err) if policy.max_attempts.is_some_and(|max| attempt >= max) => {
+                    return Err(err)
+                }
+                Err(err) => {
+                    tracing::warn!("Reconnection #{attempt} to `{}` failed: {err}", self.source)
+                }
+            }
+        }
+    }
+
+    async fn attempt(
+        &mut self,
+        policy: &Reconnect,
+        slot: &mut Option<Scan>,
+    ) -> Result<(Stream, Peer)> {
+        if let Some(config) = &policy.rescan {
+            // Keep the same scan across attempts, so it does not start from scratch each time.
+            let scan = match slot.take() {
+                Some(scan) => scan,
+                None => Scan::new(config.clone())?,
+            };
+            let scan = slot.insert(scan);
+
+            self.targets = scan
+                .wait_for(&self.source, RESCAN_TIMEOUT)
+                .await?
+                .socket_addrs();
+        }
+
+        self.connect().await
+    }
+}
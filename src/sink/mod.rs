@@ -1,80 +1,226 @@
 //! Everything related to NDI [`Sink`]s, to receive video.
 
-use std::net::SocketAddr;
+use std::sync::Arc;
 
-use ffmpeg::codec;
-use futures::TryFutureExt;
+use futures::{StreamExt, TryFutureExt, TryStreamExt};
 use itertools::Itertools;
-use mdns_sd::ServiceInfo;
-use tokio::net::TcpStream;
+use tokio::{
+    net::ToSocketAddrs,
+    sync::{broadcast, watch},
+};
 
 use crate::{
     io::{
-        frame::{audio, text::Metadata, video, Frame},
-        Stream,
+        frame::{audio, text, video, Frame},
+        Reader, Stream,
     },
-    Error, Result,
+    Error, Result, SourceInfo,
 };
 
 mod config;
-pub use config::Config;
+pub use config::{Config, Reconnect};
 
 mod peer;
 pub use peer::Peer;
 
+mod address;
+pub use address::Address;
+
+mod decoder;
+use decoder::VideoDecoder;
+
+mod event;
+pub use event::Event;
+use event::Senders;
+
+mod link;
+use link::Link;
+
+mod packet;
+pub use packet::{AudioPacket, VideoPacket};
+
+/// The capacity of the [`Event`] channel, before lagging subscribers miss some.
+const EVENTS: usize = 32;
+
 /// A _video_ and _audio_ sink, that can receive data from a source.
 #[derive(Debug, Clone)]
 pub struct Sink {
-    peer: Peer,
+    peer: watch::Receiver<Peer>,
 
     video: flume::Receiver<video::Block>,
     audio: flume::Receiver<audio::Block>,
+    metadata: flume::Receiver<text::Message>,
+
+    outgoing: flume::Sender<Frame>,
+    tally: watch::Receiver<Option<text::Tally>>,
+    events: broadcast::Sender<Event>,
 }
 
 impl Sink {
-    /// Create a new [`Sink`] based on the provided `config` and `service` entry.
-    pub async fn new(service: &ServiceInfo, config: Config<'_>) -> Result<Self> {
-        let addresses = service
-            .get_addresses()
-            .iter()
-            .map(|addr| SocketAddr::new(*addr, service.get_port()))
-            .collect::<Vec<_>>();
-        let mut stream: Stream = TcpStream::connect(addresses.as_slice()).await?.into();
-
-        let peer = tokio::time::timeout(
-            crate::HANDSHAKE_TIMEOUT,
-            Peer::handshake(&mut stream, &config),
-        )
-        .await??;
+    /// Create a new [`Sink`] based on the provided `config` and `source` information.
+    pub async fn new(source: &SourceInfo, config: Config<'_>) -> Result<Self> {
+        Self::connect(source.socket_addrs().as_slice(), config).await
+    }
 
-        let (videotx, video) = flume::bounded(config.video_queue);
-        let (audiotx, audio) = flume::bounded(config.audio_queue);
+    /// Create a new [`Sink`] based on the provided `config`, connecting directly
+    /// to the source at `addr` without any discovery.
+    pub async fn connect(addr: impl ToSocketAddrs, config: Config<'_>) -> Result<Self> {
+        Self::open(addr, None, config).await
+    }
+
+    /// Create a new [`Sink`] based on the provided `config`, connecting directly
+    /// to the source at the `address` parsed from `ndi://host:port/name` without any discovery.
+    ///
+    /// When the `address` holds a name, [`Error::UnexpectedSource`] is returned
+    /// if the source listening at this address identifies with another name.
+    pub async fn connect_to(address: &Address, config: Config<'_>) -> Result<Self> {
+        Self::open(address.socket_addr(), address.name.clone(), config).await
+    }
+
+    async fn open(
+        addr: impl ToSocketAddrs,
+        expected: Option<String>,
+        config: Config<'_>,
+    ) -> Result<Self> {
+        let mut link = Link {
+            source: Default::default(),
+            name: config.name.map(Into::into),
+            targets: tokio::net::lookup_host(addr).await?.collect(),
+            expected,
+            config: config.detached(),
+        };
+
+        let (stream, peer) = link.connect().await?;
+        link.source = peer.identify.name.clone();
+
+        let (sink, senders, outgoing) = Self::channels(peer, &config);
         tokio::spawn(
-            Self::task(stream, videotx, audiotx)
+            Self::task(stream, link, senders, outgoing)
                 .inspect_err(|err| tracing::error!("Fatal error in `Sink::task`: {err}")),
         );
 
-        Ok(Self { peer, video, audio })
+        Ok(sink)
     }
 
-    /// Access the source [`Peer`] definition.
-    pub fn peer(&self) -> &Peer {
-        &self.peer
+    /// Create the [`Sink`] handle with it's queues sized from the `config`,
+    /// along with the [`Senders`] and the `outgoing` receiver to hand to the task.
+    fn channels(peer: Peer, config: &Config<'_>) -> (Self, Senders, flume::Receiver<Frame>) {
+        let (videotx, video) = flume::bounded(config.video_queue);
+        let (audiotx, audio) = flume::bounded(config.audio_queue);
+        let (metadatatx, metadata) = flume::bounded(config.metadata_queue);
+        let (outgoing, outgoingrx) = flume::unbounded();
+        let (peertx, peer) = watch::channel(peer);
+        let (tallytx, tally) = watch::channel(None);
+        let (events, _) = broadcast::channel(EVENTS);
+
+        let senders = Senders {
+            peer: peertx,
+            video: videotx,
+            audio: audiotx,
+            metadata: metadatatx,
+            tally: tallytx,
+            events: events.clone(),
+        };
+
+        (
+            Self {
+                peer,
+                video,
+                audio,
+                metadata,
+                outgoing,
+                tally,
+                events,
+            },
+            senders,
+            outgoingrx,
+        )
+    }
+
+    /// Access the source [`Peer`] definition, as of the last successful handshake,
+    /// which is updated when a [`Reconnect`] policy restores the connection.
+    pub fn peer(&self) -> Peer {
+        self.peer.borrow().clone()
     }
 
     async fn task(
         mut stream: Stream,
-        video: flume::Sender<video::Block>,
-        audio: flume::Sender<audio::Block>,
+        mut link: Link,
+        senders: Senders,
+        outgoing: flume::Receiver<Frame>,
     ) -> Result {
         loop {
-            if video.is_disconnected() && audio.is_disconnected() {
+            let err = match Self::session(stream, &senders, &outgoing).await {
+                Ok(()) => break Ok(()),
+                Err(err) => err,
+            };
+
+            let Some(policy) = link.config.reconnect.clone() else {
+                break Err(err);
+            };
+
+            tracing::warn!("Lost connection to `{}`: {err}", link.source);
+            senders.events.send(Event::Disconnected(Arc::new(err))).ok();
+
+            stream = match link.reconnect(&policy, &senders.events, &outgoing).await {
+                Ok(Some((stream, peer))) => {
+                    senders.peer.send_replace(peer.clone());
+                    senders.events.send(Event::Reconnected(peer)).ok();
+
+                    stream
+                }
+                Ok(None) => break Ok(()),
+                Err(err) => {
+                    senders.events.send(Event::Lost).ok();
+
+                    break Err(err);
+                }
+            };
+        }
+    }
+
+    /// Exchange with the source over the `stream`, until the connection is closed
+    /// or all the `Sink` handles are dropped.
+    async fn session(
+        stream: Stream,
+        senders: &Senders,
+        outgoing: &flume::Receiver<Frame>,
+    ) -> Result {
+        let (mut reader, mut writer) = stream.into_split();
+
+        let send = async {
+            while let Ok(frame) = outgoing.recv_async().await {
+                writer.send(&frame).await?;
+            }
+
+            Ok::<_, Error>(())
+        };
+
+        // Stop sending as soon as the receiving half terminates, since the `outgoing`
+        // channel is kept open for as long as the `Sink` lives.
+        tokio::select! {
+            res = Self::receive(&mut reader, senders) => res,
+            res = send => res,
+        }
+    }
+
+    async fn receive(reader: &mut Reader, senders: &Senders) -> Result {
+        let Senders {
+            video,
+            audio,
+            metadata,
+            tally,
+            ..
+        } = senders;
+
+        loop {
+            if video.is_disconnected() && audio.is_disconnected() && metadata.is_disconnected() {
                 tracing::trace!("All receivers dropped, disconnecting from peer");
 
                 break Ok(());
             }
 
-            match stream.recv().await? {
+            match reader.recv().await? {
                 Frame::Video(block) => {
                     if let Err(err) = video.try_send(block) {
                         tracing::debug!("A video block was dropped: {err}");
@@ -86,59 +232,103 @@ impl Sink {
                     }
                 }
                 Frame::Text(block) => {
-                    let Ok(info) = Metadata::from_block(&block) else {
-                        tracing::warn!(
-                            "Unhandled information: {}",
-                            String::from_utf8_lossy(&block.data)
-                        );
+                    let message = text::Message::from_block(&block);
+
+                    tracing::trace!("Received information: {message:?}");
 
-                        continue;
-                    };
+                    if let text::Message::Known(text::Metadata::TallyEcho(echo)) = &message {
+                        tally.send_replace(Some(echo.clone()));
+                    }
 
-                    tracing::warn!("Received information: {info:?}");
+                    if let Err(err) = metadata.try_send(message) {
+                        tracing::debug!("A metadata message was dropped: {err}");
+                    }
                 }
             }
         }
     }
 
+    /// Send arbitrary `xml` metadata to the source.
+    ///
+    /// The `xml` must be well-formed, or [`Error::Xml`] is returned without sending anything.
+    pub async fn send_metadata(&self, xml: &str) -> Result {
+        let frame = Frame::metadata(xml)?;
+
+        self.outgoing
+            .send_async(frame)
+            .await
+            .map_err(|_| Error::ClosedChannel)
+    }
+
+    /// Send the `tally` state of the [`Sink`] to the source, to tell it whether it is
+    /// currently _on program_ or _on preview_.
+    ///
+    /// The source acknowledges it with an echo, exposed by [`Sink::tally`].
+    pub async fn set_tally(&self, tally: text::Tally) -> Result {
+        self.outgoing
+            .send_async(Frame::tally(tally))
+            .await
+            .map_err(|_| Error::ClosedChannel)
+    }
+
+    /// Get the last _tally_ echoed back by the source, if any.
+    pub fn tally(&self) -> Option<text::Tally> {
+        self.tally.borrow().clone()
+    }
+
+    /// Wait for the source to echo a new _tally_ back, and return it.
+    pub async fn tally_changed(&mut self) -> Result<Option<text::Tally>> {
+        self.tally
+            .changed()
+            .await
+            .map_err(|_| Error::ClosedChannel)?;
+
+        Ok(self.tally.borrow_and_update().clone())
+    }
+
+    /// Stream the [`Event`]s of the connection to the source, as it is lost and restored,
+    /// which only happens when a [`Reconnect`] policy is configured.
+    ///
+    /// Events are only emitted from the moment this method is called, and the oldest ones
+    /// are skipped if the stream is not polled fast enough.
+    pub fn events(&self) -> impl futures::Stream<Item = Event> {
+        futures::stream::unfold(self.events.subscribe(), |mut events| async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => break Some((event, events)),
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        tracing::warn!(
+                            "Skipped {count} connection event(s), as the stream lagged behind"
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break None,
+                }
+            }
+        })
+    }
+
     /// Iterate over incoming [`video::Block`]s.
     fn video_blocks(&self) -> impl Iterator<Item = Result<video::Block, flume::RecvError>> + '_ {
         std::iter::from_fn(move || Some(self.video.recv()))
     }
 
     /// Iterate over decoded [`ffmpeg::frame::Video`] from incoming blocks.
+    ///
+    /// Frames sent with an alpha channel are decoded as [`ffmpeg::format::Pixel::YUVA422P`],
+    /// with the alpha plane preserved.
     pub fn video_frames(&self) -> impl Iterator<Item = Result<ffmpeg::frame::Video>> + '_ {
+        let mut decoder = VideoDecoder::default();
+
         self.video_blocks()
-            .map(|block| {
+            .map(move |block| {
                 let block = block.map_err(|_| Error::ClosedChannel)?;
 
-                tracing::trace!("<- new block {block:?} from `{}`", self.peer.identify.name);
-
-                let mut context = codec::Context::new();
-                // SAFETY: The pointer is allocated on the line before,
-                // and is guaranteed to be exclusive with `as_mut_ptr`.
-                unsafe {
-                    (*context.as_mut_ptr()).codec_tag = block.header.fourcc.to_code();
-                    (*context.as_mut_ptr()).width = block.header.width as i32;
-                    (*context.as_mut_ptr()).height = block.header.height as i32;
-                    (*context.as_mut_ptr()).framerate = ffmpeg::ffi::AVRational {
-                        num: block.header.fps_num as i32,
-                        den: block.header.fps_den as i32,
-                    };
-                }
-
-                let mut decoder = context
-                    .decoder()
-                    .open_as(codec::decoder::find(codec::Id::SPEEDHQ))?
-                    .video()?;
+                tracing::trace!(
+                    "<- new block {block:?} from `{}`",
+                    self.peer.borrow().identify.name
+                );
 
-                decoder.send_packet(&codec::packet::Packet::borrow(&block.data))?;
-                decoder.send_eof()?;
-
-                Ok::<_, crate::Error>(std::iter::from_fn(move || {
-                    let mut frame = ffmpeg::frame::Video::empty();
-                    decoder.receive_frame(&mut frame).is_ok().then_some(frame)
-                }))
+                decoder.decode(&block)
             })
             .flatten_ok()
     }
@@ -150,32 +340,359 @@ impl Sink {
 
     /// Iterate over decoded [`ffmpeg::frame::Audio`] from incoming blocks.
     pub fn audio_frames(&self) -> impl Iterator<Item = Result<ffmpeg::frame::Audio>> + '_ {
-        self.audio_blocks()
-            .map(|block| {
-                let block = block.map_err(|_| Error::ClosedChannel)?;
+        self.audio_blocks().map(|block| {
+            let block = block.map_err(|_| Error::ClosedChannel)?;
+
+            tracing::trace!(
+                "<- new block {block:?} from `{}`",
+                self.peer.borrow().identify.name
+            );
+
+            decoder::audio(&block)
+        })
+    }
+
+    /// Iterate over incoming [`VideoPacket`]s, still compressed, to record or relay them without decoding.
+    pub fn video_packets(&self) -> impl Iterator<Item = Result<VideoPacket>> + '_ {
+        self.video_blocks()
+            .map(|block| VideoPacket::from_block(block.map_err(|_| Error::ClosedChannel)?))
+    }
+
+    /// Iterate over incoming [`AudioPacket`]s, to record or relay them without decoding.
+    pub fn audio_packets(&self) -> impl Iterator<Item = Result<AudioPacket>> + '_ {
+        self.audio_blocks().map(|block| {
+            Ok(AudioPacket::from_block(
+                block.map_err(|_| Error::ClosedChannel)?,
+            ))
+        })
+    }
+
+    /// Iterate over incoming [`text::Message`]s.
+    pub fn metadata_frames(&self) -> impl Iterator<Item = Result<text::Message>> + '_ {
+        std::iter::from_fn(move || Some(self.metadata.recv().map_err(|_| Error::ClosedChannel)))
+    }
+
+    /// Stream decoded [`ffmpeg::frame::Video`] from incoming blocks, ending when the source disconnects.
+    ///
+    /// Decoding is offloaded to the blocking thread pool, so the async runtime is never stalled.
+    pub fn video_stream(&self) -> impl futures::Stream<Item = Result<ffmpeg::frame::Video>> {
+        let name = self.peer.borrow().identify.name.clone();
+
+        futures::stream::unfold(
+            (self.video.clone(), VideoDecoder::default()),
+            move |(video, mut decoder)| {
+                let name = name.clone();
+
+                async move {
+                    let block = video.recv_async().await.ok()?;
 
-                let mut context = codec::Context::new();
-                // SAFETY: The pointer is allocated on the line before,
-                // and is guaranteed to be exclusive with `as_mut_ptr`.
-                unsafe {
-                    (*context.as_mut_ptr()).codec_tag = block.header.fourcc.to_code();
-                    (*context.as_mut_ptr()).channels = block.header.num_channels as i32;
-                    (*context.as_mut_ptr()).sample_rate = block.header.sample_rate as i32;
+                    tracing::trace!("<- new block {block:?} from `{name}`");
+
+                    let (decoder, frames) = tokio::task::spawn_blocking(move || {
+                        let frames = decoder.decode(&block);
+
+                        (decoder, frames)
+                    })
+                    .await
+                    .unwrap_or_else(|err| (VideoDecoder::default(), Err(err.into())));
+
+                    Some((frames, (video, decoder)))
                 }
+            },
+        )
+        .map_ok(|frames| futures::stream::iter(frames).map(Ok::<_, Error>))
+        .try_flatten()
+    }
 
-                let mut decoder = context
-                    .decoder()
-                    .open_as(block.header.fourcc.to_codec())?
-                    .audio()?;
+    /// Stream decoded [`ffmpeg::frame::Audio`] from incoming blocks, ending when the source disconnects.
+    pub fn audio_stream(&self) -> impl futures::Stream<Item = Result<ffmpeg::frame::Audio>> {
+        let name = self.peer.borrow().identify.name.clone();
 
-                decoder.send_packet(&codec::packet::Packet::borrow(&block.data))?;
-                decoder.send_eof()?;
+        self.audio.clone().into_stream().map(move |block| {
+            tracing::trace!("<- new block {block:?} from `{name}`");
 
-                Ok::<_, crate::Error>(std::iter::from_fn(move || {
-                    let mut frame = ffmpeg::frame::Audio::empty();
-                    decoder.receive_frame(&mut frame).is_ok().then_some(frame)
-                }))
-            })
-            .flatten_ok()
+            decoder::audio(&block)
+        })
+    }
+
+    /// Stream incoming [`VideoPacket`]s, still compressed, ending when the source disconnects.
+    pub fn video_packet_stream(&self) -> impl futures::Stream<Item = Result<VideoPacket>> {
+        self.video
+            .clone()
+            .into_stream()
+            .map(VideoPacket::from_block)
+    }
+
+    /// Stream incoming [`AudioPacket`]s, ending when the source disconnects.
+    pub fn audio_packet_stream(&self) -> impl futures::Stream<Item = AudioPacket> {
+        self.audio
+            .clone()
+            .into_stream()
+            .map(AudioPacket::from_block)
+    }
+
+    /// Stream incoming [`text::Message`]s, ending when the source disconnects.
+    pub fn metadata_stream(&self) -> impl futures::Stream<Item = text::Message> {
+        self.metadata.clone().into_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use ffmpeg::format::{sample::Type, Sample};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        io::Packet,
+        source::{
+            encoder::{AudioEncoder, VideoEncoder},
+            VideoCodec,
+        },
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn sink() -> (Sink, Senders) {
+        let peer = Peer {
+            version: text::Version {
+                text: 3,
+                video: 5,
+                audio: 4,
+                sdk: crate::SDK_VERSION.into(),
+                platform: crate::SDK_PLATFORM.into(),
+            },
+            identify: text::Identify {
+                name: crate::name("test source"),
+            },
+        };
+        let (sink, senders, _) = Sink::channels(
+            peer,
+            &Config {
+                video_queue: 1,
+                audio_queue: 1,
+                metadata_queue: 1,
+                ..Default::default()
+            },
+        );
+
+        (sink, senders)
+    }
+
+    /// Accept a connection on the `listener` and play the source side of the handshake as `name`.
+    async fn serve(listener: &TcpListener, name: &str) -> Result<Stream> {
+        let (stream, _) = listener.accept().await?;
+        let mut stream: Stream = stream.into();
+
+        stream.send(&Frame::version()).await?;
+        stream.send(&Frame::identify(name)).await?;
+
+        Ok(stream)
+    }
+
+    fn reconnect(max_attempts: Option<usize>) -> Config<'static> {
+        Config {
+            video_queue: 1,
+            audio_queue: 1,
+            metadata_queue: 1,
+            reconnect: Some(Reconnect {
+                backoff: Duration::from_millis(50),
+                max_backoff: Duration::from_millis(100),
+                max_attempts,
+                rescan: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn transmit(frame: &ffmpeg::frame::Audio, sink: &flume::Sender<audio::Block>) -> Result {
+        match Packet::from_frame(&AudioEncoder::default().encode(frame)?).into_frame()? {
+            Frame::Audio(block) => sink.send(block).map_err(|_| Error::ClosedChannel),
+            _ => Err(Error::UnknownKind),
+        }
+    }
+
+    #[test]
+    fn it_receives_planar_float_audio() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let (sink, senders) = sink();
+
+        let mut frame = ffmpeg::frame::Audio::new(
+            Sample::F32(Type::Planar),
+            480,
+            ffmpeg::ChannelLayout::STEREO,
+        );
+        frame.set_rate(48_000);
+        for (idx, sample) in frame.plane_mut::<f32>(0).iter_mut().enumerate() {
+            *sample = (idx as f32 / 480.0).sin();
+        }
+        for (idx, sample) in frame.plane_mut::<f32>(1).iter_mut().enumerate() {
+            *sample = (idx as f32 / 480.0).cos();
+        }
+
+        transmit(&frame, &senders.audio)?;
+        let received = sink.audio_frames().next().ok_or("No frame received")??;
+
+        assert_eq!(received.format(), frame.format());
+        assert_eq!(received.rate(), frame.rate());
+        assert_eq!(received.channels(), frame.channels());
+        assert_eq!(received.samples(), frame.samples());
+        assert_eq!(received.plane::<f32>(0), frame.plane::<f32>(0));
+        assert_eq!(received.plane::<f32>(1), frame.plane::<f32>(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_receives_packed_s16_audio() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let (sink, senders) = sink();
+
+        let mut frame = ffmpeg::frame::Audio::new(
+            Sample::I16(Type::Packed),
+            480,
+            ffmpeg::ChannelLayout::STEREO,
+        );
+        frame.set_rate(44_100);
+        for (idx, sample) in frame.plane_mut::<(i16, i16)>(0).iter_mut().enumerate() {
+            *sample = (idx as i16 * 64, -(idx as i16) * 64);
+        }
+
+        transmit(&frame, &senders.audio)?;
+        let received = sink.audio_frames().next().ok_or("No frame received")??;
+
+        assert_eq!(received.format(), frame.format());
+        assert_eq!(received.rate(), frame.rate());
+        assert_eq!(received.channels(), frame.channels());
+        assert_eq!(received.samples(), frame.samples());
+        assert_eq!(
+            received.plane::<(i16, i16)>(0),
+            frame.plane::<(i16, i16)>(0)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_crops_the_padding_of_speedhq_video() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let (sink, senders) = sink();
+
+        let frame = ffmpeg::frame::Video::new(ffmpeg::format::Pixel::YUV422P, 100, 32);
+        let Some(encoded) = VideoEncoder::new(VideoCodec::SpeedHQ)
+            .encode(&frame, ffmpeg::Rational::new(30, 1))?
+            .pop()
+        else {
+            return Err("No video frame was encoded".into());
+        };
+
+        let Frame::Video(block) = Packet::from_frame(&encoded).into_frame()? else {
+            return Err("The encoded frame was not a video one".into());
+        };
+        senders
+            .video
+            .send(block)
+            .map_err(|_| Error::ClosedChannel)?;
+
+        let received = sink.video_frames().next().ok_or("No frame received")??;
+
+        assert_eq!(received.width(), 100);
+        assert_eq!(received.height(), 32);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_reconnects_and_updates_the_peer() -> Result<(), Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let (sink, first) = tokio::try_join!(
+            Sink::connect(addr, reconnect(None)),
+            serve(&listener, "first source")
+        )?;
+        assert_eq!(sink.peer().identify.name, crate::name("first source"));
+
+        let mut events = Box::pin(sink.events());
+        drop(first);
+
+        let second = tokio::time::timeout(TIMEOUT, serve(&listener, "second source")).await??;
+
+        let received = tokio::time::timeout(TIMEOUT, async {
+            let mut received = Vec::new();
+            while let Some(event) = events.next().await {
+                let done = matches!(event, Event::Reconnected(_));
+                received.push(event);
+
+                if done {
+                    break;
+                }
+            }
+
+            received
+        })
+        .await?;
+
+        assert!(matches!(
+            received.as_slice(),
+            [
+                Event::Disconnected(_),
+                Event::Reconnecting { attempt: 1 },
+                Event::Reconnected(peer),
+            ] if peer.identify.name == crate::name("second source")
+        ));
+        assert_eq!(sink.peer().identify.name, crate::name("second source"));
+
+        drop(second);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_backs_off_until_the_attempts_are_exhausted(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let (sink, stream) = tokio::try_join!(
+            Sink::connect(addr, reconnect(Some(3))),
+            serve(&listener, "vanishing source")
+        )?;
+
+        let mut events = Box::pin(sink.events());
+        drop(listener);
+        drop(stream);
+
+        let (received, lost, elapsed) = tokio::time::timeout(TIMEOUT, async {
+            let mut received = Vec::new();
+            let mut lost = false;
+            let mut disconnected = Instant::now();
+            while let Some(event) = events.next().await {
+                match event {
+                    Event::Disconnected(_) => disconnected = Instant::now(),
+                    Event::Reconnecting { attempt } => received.push(attempt),
+                    Event::Reconnected(_) => break,
+                    Event::Lost => {
+                        lost = true;
+                        break;
+                    }
+                }
+            }
+
+            (received, lost, disconnected.elapsed())
+        })
+        .await?;
+
+        // The delay doubles from `50ms` after each attempt, capped at `100ms`
+        assert_eq!(received, [1, 2, 3]);
+        assert!(lost);
+        assert!(elapsed >= Duration::from_millis(50 + 100 + 100));
+
+        Ok(())
     }
 }
@@ -0,0 +1,127 @@
+use crate::{
+    io::frame::{audio, video},
+    Result,
+};
+
+#[cfg(doc)]
+use super::Sink;
+
+/// A compressed video frame received by a [`Sink`], as sent by the source.
+#[derive(Clone)]
+pub struct VideoPacket {
+    /// The specification of the frame.
+    pub spec: video::Spec,
+
+    /// The compressed frame, with it's timestamps in [`crate::video::TIMEBASE`].
+    pub packet: ffmpeg::Packet,
+
+    /// The codec extradata sent along `H264` and `HEVC` keyframes, empty otherwise.
+    pub extradata: Vec<u8>,
+}
+
+impl std::fmt::Debug for VideoPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VideoPacket")
+            .field("spec", &self.spec)
+            .field("packet", &"...")
+            .field("extradata", &"...")
+            .finish()
+    }
+}
+
+impl VideoPacket {
+    pub(super) fn from_block(block: video::Block) -> Result<Self> {
+        if block.header.fourcc.is_inter() {
+            let inner = video::Packet::from_bytes(&block.data)?;
+
+            let mut packet = ffmpeg::Packet::copy(&inner.data);
+            packet.set_pts(Some(inner.pts));
+            packet.set_dts(Some(inner.dts));
+            if inner.keyframe {
+                packet.set_flags(ffmpeg::codec::packet::Flags::KEY);
+            }
+
+            Ok(Self {
+                spec: block.header,
+                packet,
+                extradata: inner.extradata,
+            })
+        } else {
+            // Intra-only frames are all keyframes, timed by their wall-clock timestamp.
+            let timestamp = block.header.timestamp.timestamp_micros() * 10;
+
+            let mut packet = ffmpeg::Packet::copy(&block.data);
+            packet.set_pts(Some(timestamp));
+            packet.set_dts(Some(timestamp));
+            packet.set_flags(ffmpeg::codec::packet::Flags::KEY);
+
+            Ok(Self {
+                spec: block.header,
+                packet,
+                extradata: Vec::new(),
+            })
+        }
+    }
+}
+
+/// A block of audio samples received by a [`Sink`], as sent by the source.
+#[derive(Clone)]
+pub struct AudioPacket {
+    /// The specification of the samples.
+    pub spec: audio::Spec,
+
+    /// The samples, laid out as described by [`crate::audio::FourCCAudioType`].
+    pub packet: ffmpeg::Packet,
+}
+
+impl std::fmt::Debug for AudioPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioPacket")
+            .field("spec", &self.spec)
+            .field("packet", &"...")
+            .finish()
+    }
+}
+
+impl AudioPacket {
+    pub(super) fn from_block(block: audio::Block) -> Self {
+        Self {
+            packet: ffmpeg::Packet::copy(&block.data),
+            spec: block.header,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::frame::Block;
+
+    #[test]
+    fn it_unwraps_inter_frame_packets() -> Result {
+        let inner = video::Packet {
+            fourcc: video::FourCCVideoType::H264,
+            pts: 400_000,
+            dts: 0,
+            keyframe: true,
+            data: vec![0, 0, 0, 1, 0x65, 0x88],
+            extradata: vec![0, 0, 0, 1, 0x67, 0x42],
+        };
+        let block = Block {
+            header: video::Spec {
+                fourcc: video::FourCCVideoType::H264,
+                ..Default::default()
+            },
+            data: inner.to_bytes().into(),
+        };
+
+        let packet = VideoPacket::from_block(block)?;
+
+        assert_eq!(packet.packet.data(), Some(inner.data.as_slice()));
+        assert_eq!(packet.packet.pts(), Some(inner.pts));
+        assert!(packet.packet.is_key());
+        assert_eq!(packet.extradata, inner.extradata);
+
+        Ok(())
+    }
+}
@@ -43,7 +43,7 @@ impl Peer {
         Ok(())
     }
 
-    pub(super) async fn handshake(stream: &mut Stream, config: &Config<'_>) -> Result<Self> {
+    pub(crate) async fn handshake(stream: &mut Stream, config: &Config<'_>) -> Result<Self> {
         Self::greet(stream, config).await?;
 
         let mut version = None;
@@ -1,12 +1,143 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
+};
+
+use if_addrs::IfAddr;
+
+use crate::{io::frame::text, Error, Result};
+
 #[cfg(doc)]
-use super::Source;
+use super::{Peer, Source};
 
 /// Configuration for the [`Source`] structure.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct Config {
     /// Source name to advertise over the network.
     pub name: String,
 
     /// Source groups to advertise over the network, defaults to `public`.
     pub groups: Option<Vec<&'static str>>,
+
+    /// Size of the video queue retained for each [`Peer`] until frames are dropped, defaults to `4`.
+    pub video_queue: usize,
+
+    /// Size of the audio queue retained for each [`Peer`] until it is disconnected, defaults to `32`.
+    pub audio_queue: usize,
+
+    /// Size of the metadata queue retained for each [`Peer`] until it is disconnected, defaults to `32`.
+    pub metadata_queue: usize,
+
+    /// Policy applied to the video frames of a [`Peer`] which cannot keep up with the stream.
+    pub drop_policy: DropPolicy,
+
+    /// Codec used to compress the video frames sent to the peers, defaults to SpeedHQ.
+    pub video_codec: VideoCodec,
+
+    /// Quality requested by the peers the pre-compressed packets of [`Source::broadcast_video_packet`]
+    /// are sent to, defaults to [`text::VideoQuality::High`], leaving the other peers without video.
+    ///
+    /// Set to `None` to send them to all the peers, including the ones requesting
+    /// [`text::VideoQuality::Low`], which then receive the full bitrate.
+    pub passthrough_quality: Option<text::VideoQuality>,
+
+    /// `nndi` discovery server to register to instead of advertising over mDNS, see [`crate::discovery`].
+    pub discovery: Option<SocketAddr>,
+
+    /// Address to listen for peers on, defaults to all the addresses of the allowed interfaces.
+    pub bind: Option<IpAddr>,
+
+    /// Ports to try listening on, in order, such as `5961..=5970` or `5961..=5961` for a fixed one,
+    /// defaults to a port picked by the system.
+    pub ports: Option<RangeInclusive<u16>>,
+
+    /// Network interfaces to listen and advertise on, by name (such as `eth0`),
+    /// defaults to all of them.
+    ///
+    /// IPv6 _link-local_ addresses of the interfaces are not used, as they are scoped.
+    pub interfaces: Option<Vec<String>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            name: Default::default(),
+            groups: None,
+            video_queue: 4,
+            audio_queue: 32,
+            metadata_queue: 32,
+            drop_policy: Default::default(),
+            video_codec: Default::default(),
+            passthrough_quality: Some(text::VideoQuality::High),
+            discovery: None,
+            bind: None,
+            ports: None,
+            interfaces: None,
+        }
+    }
+}
+
+impl Config {
+    /// The specific addresses to listen and advertise on, if restricted by the configuration.
+    pub(super) fn addresses(&self) -> Result<Option<Vec<IpAddr>>> {
+        if let Some(bind) = self.bind.filter(|bind| !bind.is_unspecified()) {
+            return Ok(Some(vec![bind]));
+        }
+
+        let Some(interfaces) = &self.interfaces else {
+            return Ok(None);
+        };
+
+        let addresses: Vec<_> = if_addrs::get_if_addrs()?
+            .into_iter()
+            .filter(|iface| interfaces.contains(&iface.name))
+            .filter(|iface| !(matches!(iface.addr, IfAddr::V6(_)) && iface.is_link_local()))
+            .map(|iface| iface.ip())
+            .filter(|ip| match self.bind {
+                Some(bind) => bind.is_ipv4() == ip.is_ipv4(),
+                None => true,
+            })
+            .collect();
+
+        if addresses.is_empty() {
+            return Err(Error::NoInterface(interfaces.join(", ")));
+        }
+
+        Ok(Some(addresses))
+    }
+}
+
+/// Policy applied to the video frames of a [`Peer`] which cannot keep up with the stream,
+/// _audio_ and _metadata_ frames are never dropped, and the peer is disconnected instead
+/// once their queue is full.
+///
+/// Since the frames of [`VideoCodec::H264`] reference the previous ones, once one of them is dropped
+/// the queued ones are discarded with it under [`DropPolicy::DropOldest`], and the following ones
+/// are dropped under either policy until the next keyframe.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Drop the oldest queued video frame to make room for the newest one.
+    #[default]
+    DropOldest,
+
+    /// Drop the newest video frame, keeping the queued ones.
+    DropNewest,
+}
+
+/// Codec used by a [`Source`] to compress it's video frames.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    /// Intra-frame SpeedHQ, as sent by regular NDI sources.
+    #[default]
+    SpeedHQ,
+
+    /// Long-GOP H.264 from a software encoder, trading latency and decoding cost
+    /// for a much lower bandwidth.
+    ///
+    /// The frames are wrapped in a layout specific to this crate, not the one of _NDI|HX_,
+    /// so only `nndi` sinks can receive them.
+    ///
+    /// A keyframe is sent every second, and once a video frame is dropped for a peer,
+    /// the following ones are dropped too until the next keyframe.
+    H264,
 }
@@ -0,0 +1,548 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use futures::{FutureExt, TryFutureExt};
+use tokio::{sync::Notify, task::JoinHandle};
+
+use crate::{
+    io::{
+        frame::{text, video, Frame, FrameKind},
+        Reader, Stream, Writer,
+    },
+    Error, Result,
+};
+
+use super::{Config, DropPolicy, Lock, Peer};
+
+/// A change in the state of a [`Connection`], reported to the listener of the `Source`.
+#[derive(Debug)]
+pub enum Notification {
+    /// The peer sent the `current` _tally_, replacing the `previous` one.
+    Tally {
+        previous: text::Tally,
+        current: text::Tally,
+    },
+
+    /// The peer changed it's requested _quality_ or _enabled streams_.
+    Updated,
+
+    /// The connection task terminated, with the error which caused it, if any.
+    Closed(Option<Arc<Error>>),
+}
+
+/// The bounded outbound video queue of a [`Connection`], applying the [`DropPolicy`] when it is full.
+struct Queue {
+    sender: flume::Sender<Arc<Frame>>,
+    receiver: flume::Receiver<Arc<Frame>>,
+    policy: DropPolicy,
+
+    /// Whether an inter-frame video frame was dropped, so that the following ones
+    /// are dropped too until the next keyframe, as they would not decode.
+    broken: AtomicBool,
+}
+
+impl Queue {
+    fn new(size: usize, policy: DropPolicy) -> Self {
+        let (sender, receiver) = flume::bounded(size.max(1));
+
+        Self {
+            sender,
+            receiver,
+            policy,
+            broken: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the `frame` is a keyframe of an inter-frame codec, or `None` if it's a standalone frame.
+    fn keyframe(frame: &Frame) -> Option<bool> {
+        match frame {
+            Frame::Video(block) if block.header.fourcc.is_inter() => {
+                Some(video::Packet::is_keyframe(&block.data).unwrap_or_default())
+            }
+            _ => None,
+        }
+    }
+
+    /// Queue the `frame`, returning how many frames were dropped because the queue was full.
+    fn push(&self, frame: Arc<Frame>) -> u64 {
+        let keyframe = Self::keyframe(&frame);
+
+        if keyframe == Some(false) && self.broken.load(Ordering::Relaxed) {
+            return 1;
+        }
+
+        let frame = match self.sender.try_send(frame) {
+            Err(flume::TrySendError::Full(frame)) => frame,
+            _ => {
+                if keyframe == Some(true) {
+                    self.broken.store(false, Ordering::Relaxed);
+                }
+
+                return 0;
+            }
+        };
+
+        match (self.policy, keyframe) {
+            (DropPolicy::DropOldest, None) => {
+                self.receiver.try_recv().ok();
+                self.sender.try_send(frame).ok();
+
+                1
+            }
+            // The queued frames would reference the dropped one, restart from a keyframe.
+            (DropPolicy::DropOldest, Some(keyframe)) => {
+                let dropped = self.receiver.drain().count() as u64;
+                self.broken.store(!keyframe, Ordering::Relaxed);
+
+                if keyframe {
+                    self.sender.try_send(frame).ok();
+
+                    dropped
+                } else {
+                    dropped + 1
+                }
+            }
+            (DropPolicy::DropNewest, keyframe) => {
+                if keyframe.is_some() {
+                    self.broken.store(true, Ordering::Relaxed);
+                }
+
+                1
+            }
+        }
+    }
+}
+
+/// A connection to a [`Peer`], with it's own outbound queues and task,
+/// so that a slow peer cannot stall the delivery to the other ones.
+///
+/// Only video frames are dropped when the peer cannot keep up, once the _audio_ or _metadata_
+/// queue is full the peer is disconnected instead, as these are never dropped.
+pub struct Connection {
+    peer: Lock<Peer>,
+
+    video: Queue,
+    audio: flume::Sender<Arc<Frame>>,
+    metadata: flume::Sender<Arc<Frame>>,
+
+    /// Notified when a queue which is never dropped from is full, to disconnect the peer.
+    overflow: Arc<Notify>,
+
+    task: JoinHandle<()>,
+}
+
+type Notifier = flume::Sender<(u64, Notification)>;
+
+impl Connection {
+    /// Spawn the connection task for the `peer` over the provided `stream`,
+    /// reporting it's [`Notification`]s tagged with it's `id` to the `notifier`.
+    pub fn spawn(
+        id: u64,
+        peer: Lock<Peer>,
+        stream: Stream,
+        config: &Config,
+        notifier: Notifier,
+    ) -> Self {
+        let video = Queue::new(config.video_queue, config.drop_policy);
+        let (audio, audiorx) = flume::bounded(config.audio_queue.max(1));
+        let (metadata, metadatarx) = flume::bounded(config.metadata_queue.max(1));
+        let (replies, repliesrx) = flume::bounded(config.metadata_queue.max(1));
+        let overflow = Arc::new(Notify::new());
+
+        let task = tokio::spawn(
+            Self::task(
+                id,
+                peer.clone(),
+                stream,
+                [repliesrx, metadatarx, audiorx, video.receiver.clone()],
+                replies,
+                overflow.clone(),
+                notifier.clone(),
+            )
+            .inspect_err(|err| tracing::error!("Peer handling failed: {err}"))
+            .map(move |res| {
+                notifier
+                    .send((id, Notification::Closed(res.err().map(Arc::new))))
+                    .ok();
+            }),
+        );
+
+        Self {
+            peer,
+            video,
+            audio,
+            metadata,
+            overflow,
+            task,
+        }
+    }
+
+    async fn task(
+        id: u64,
+        peer: Lock<Peer>,
+        stream: Stream,
+        queues: [flume::Receiver<Arc<Frame>>; 4],
+        replies: flume::Sender<Arc<Frame>>,
+        overflow: Arc<Notify>,
+        notifier: Notifier,
+    ) -> Result {
+        let (mut reader, mut writer) = stream.into_split();
+
+        tokio::try_join!(
+            Self::receive(id, &peer, &mut reader, replies, notifier),
+            Self::send(&mut writer, &queues),
+            async {
+                overflow.notified().await;
+
+                Err::<(), _>(Error::Overflow)
+            }
+        )?;
+
+        Ok(())
+    }
+
+    /// Receive metadata from the peer, until the connection is closed.
+    async fn receive(
+        id: u64,
+        peer: &Lock<Peer>,
+        reader: &mut Reader,
+        replies: flume::Sender<Arc<Frame>>,
+        notifier: Notifier,
+    ) -> Result {
+        loop {
+            match reader.metadata().await? {
+                Some(text::Metadata::Tally(tally)) => {
+                    let previous = std::mem::replace(&mut peer.write().await.tally, tally.clone());
+                    if previous != tally {
+                        let current = tally.clone();
+
+                        notifier
+                            .send((id, Notification::Tally { previous, current }))
+                            .ok();
+                    }
+
+                    // Acknowledge the tally to the peer, as it expects an echo of it,
+                    // holding back the reading until the writer makes room for it.
+                    replies
+                        .send_async(Arc::new(Frame::tally_echo(tally)))
+                        .await
+                        .map_err(|_| Error::ClosedChannel)?;
+                }
+                Some(text::Metadata::Video(video)) => {
+                    let previous =
+                        std::mem::replace(&mut peer.write().await.quality, video.quality);
+                    if previous != video.quality {
+                        notifier.send((id, Notification::Updated)).ok();
+                    }
+                }
+                Some(text::Metadata::EnabledStreams(streams)) => {
+                    peer.write().await.streams = streams;
+
+                    notifier.send((id, Notification::Updated)).ok();
+                }
+                other => tracing::debug!("Ignored metadata from peer: {other:?}"),
+            }
+        }
+    }
+
+    /// Send replies and queued frames to the peer, until the queues are closed.
+    async fn send(writer: &mut Writer, queues: &[flume::Receiver<Arc<Frame>>; 4]) -> Result {
+        let [replies, metadata, audio, video] = queues;
+
+        loop {
+            tokio::select! {
+                biased;
+
+                // Send replies to the peer's metadata first
+                Ok(frame) = replies.recv_async() => writer.send(&frame).await?,
+
+                // Then metadata and audio frames, as they are light
+                Ok(frame) = metadata.recv_async() => writer.send(&frame).await?,
+                Ok(frame) = audio.recv_async() => writer.send(&frame).await?,
+
+                // Then send the queued video frames
+                Ok(frame) = video.recv_async() => writer.send(&frame).await?,
+
+                else => break Ok(()),
+            }
+        }
+    }
+
+    /// Access the current state of the connected [`Peer`].
+    pub async fn peer(&self) -> Peer {
+        self.peer.read().await.clone()
+    }
+
+    /// Whether the `peer` wants the `frame`, intended for the peers requesting the `quality`, or all of them if `None`.
+    fn wants(peer: &Peer, frame: &Frame, quality: &Option<text::VideoQuality>) -> bool {
+        match frame {
+            Frame::Text(_) => peer.streams.text,
+            Frame::Video(_) => {
+                peer.streams.video
+                    && quality
+                        .as_ref()
+                        .map_or(true, |quality| *quality == peer.quality)
+            }
+            Frame::Audio(_) => peer.streams.audio,
+        }
+    }
+
+    /// Queue the `frame` for sending to the peer if it is interested in it, applying the configured
+    /// [`DropPolicy`] to video frames, or disconnecting the peer, if it cannot keep up.
+    pub async fn push(&self, frame: &Arc<Frame>, quality: &Option<text::VideoQuality>) {
+        let peer = self.peer.read().await;
+
+        if !Self::wants(&peer, frame, quality) {
+            tracing::trace!(
+                "-x-> skip sending {:?} frame to `{}`",
+                FrameKind::from(frame.as_ref()),
+                peer.identify.name
+            );
+
+            return;
+        }
+
+        tracing::trace!("-> queuing {:?} frame to `{}`", frame, peer.identify.name);
+        drop(peer);
+
+        let queue = match frame.as_ref() {
+            Frame::Video(_) => {
+                let count = self.video.push(frame.clone());
+                if count > 0 {
+                    let mut peer = self.peer.write().await;
+                    peer.dropped_video += count;
+
+                    tracing::debug!(
+                        "A video frame was dropped for `{}`, {} dropped so far",
+                        peer.identify.name,
+                        peer.dropped_video
+                    );
+                }
+
+                return;
+            }
+            Frame::Audio(_) => &self.audio,
+            Frame::Text(_) => &self.metadata,
+        };
+
+        if let Err(flume::TrySendError::Full(_)) = queue.try_send(frame.clone()) {
+            tracing::warn!(
+                "Disconnecting `{}`, as it cannot keep up with the {:?} frames",
+                self.peer.read().await.identify.name,
+                FrameKind::from(frame.as_ref())
+            );
+
+            self.overflow.notify_one();
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{
+        net::{TcpListener, TcpStream},
+        sync::RwLock,
+    };
+
+    use super::*;
+
+    fn peer(quality: text::VideoQuality) -> Peer {
+        Peer {
+            version: text::Version {
+                text: 3,
+                video: 5,
+                audio: 4,
+                sdk: crate::SDK_VERSION.into(),
+                platform: crate::SDK_PLATFORM.into(),
+            },
+            identify: text::Identify {
+                name: crate::name("test sink"),
+            },
+            streams: text::EnabledStreams {
+                text: true,
+                video: true,
+                audio: true,
+                shq_skip_block: false,
+                shq_short_dc: false,
+            },
+            quality,
+            tally: Default::default(),
+            dropped_video: 0,
+        }
+    }
+
+    fn frame(index: u8) -> Arc<Frame> {
+        Arc::new(Frame::video(Default::default(), vec![index]))
+    }
+
+    fn metadata(index: usize) -> Result<Arc<Frame>> {
+        Ok(Arc::new(Frame::metadata(&format!(
+            "<frame index=\"{index}\"/>"
+        ))?))
+    }
+
+    fn inter(index: u8, keyframe: bool) -> Arc<Frame> {
+        Arc::new(Frame::video(
+            video::Spec {
+                fourcc: video::FourCCVideoType::H264,
+                ..Default::default()
+            },
+            video::Packet {
+                fourcc: video::FourCCVideoType::H264,
+                keyframe,
+                data: vec![index],
+                ..Default::default()
+            }
+            .to_bytes(),
+        ))
+    }
+
+    fn drain(queue: &Queue) -> Vec<Arc<Frame>> {
+        queue.receiver.drain().collect()
+    }
+
+    #[test]
+    fn it_drops_the_oldest_frames() {
+        let queue = Queue::new(2, DropPolicy::DropOldest);
+
+        assert_eq!(queue.push(frame(0)), 0);
+        assert_eq!(queue.push(frame(1)), 0);
+        assert_eq!(queue.push(frame(2)), 1);
+
+        assert_eq!(drain(&queue), [frame(1), frame(2)]);
+    }
+
+    #[test]
+    fn it_drops_the_newest_frames() {
+        let queue = Queue::new(2, DropPolicy::DropNewest);
+
+        assert_eq!(queue.push(frame(0)), 0);
+        assert_eq!(queue.push(frame(1)), 0);
+        assert_eq!(queue.push(frame(2)), 1);
+
+        assert_eq!(drain(&queue), [frame(0), frame(1)]);
+    }
+
+    #[test]
+    fn it_drops_the_oldest_inter_frames_until_the_next_keyframe() {
+        let queue = Queue::new(2, DropPolicy::DropOldest);
+
+        assert_eq!(queue.push(inter(0, true)), 0);
+        assert_eq!(queue.push(inter(1, false)), 0);
+
+        // The queued frames are discarded along with the new one, which references them
+        assert_eq!(queue.push(inter(2, false)), 3);
+        assert_eq!(queue.push(inter(3, false)), 1);
+
+        assert_eq!(queue.push(inter(4, true)), 0);
+        assert_eq!(queue.push(inter(5, false)), 0);
+
+        assert_eq!(drain(&queue), [inter(4, true), inter(5, false)]);
+    }
+
+    #[test]
+    fn it_drops_the_newest_inter_frames_until_the_next_keyframe() {
+        let queue = Queue::new(2, DropPolicy::DropNewest);
+
+        assert_eq!(queue.push(inter(0, true)), 0);
+        assert_eq!(queue.push(inter(1, false)), 0);
+        assert_eq!(queue.push(inter(2, false)), 1);
+
+        assert_eq!(drain(&queue), [inter(0, true), inter(1, false)]);
+
+        // The queue has room again, but the frames following the dropped one would not decode
+        assert_eq!(queue.push(inter(3, false)), 1);
+        assert_eq!(queue.push(inter(4, true)), 0);
+        assert_eq!(queue.push(inter(5, false)), 0);
+
+        assert_eq!(drain(&queue), [inter(4, true), inter(5, false)]);
+    }
+
+    #[test]
+    fn it_restarts_from_a_keyframe_when_the_queue_is_full() {
+        let queue = Queue::new(2, DropPolicy::DropOldest);
+
+        assert_eq!(queue.push(inter(0, true)), 0);
+        assert_eq!(queue.push(inter(1, false)), 0);
+        assert_eq!(queue.push(inter(2, true)), 2);
+
+        assert_eq!(drain(&queue), [inter(2, true)]);
+    }
+
+    #[test]
+    fn it_sends_video_of_the_requested_quality_or_for_all_peers() {
+        let frame = inter(0, true);
+
+        let high = peer(text::VideoQuality::High);
+        let low = peer(text::VideoQuality::Low);
+
+        assert!(Connection::wants(
+            &high,
+            &frame,
+            &Some(text::VideoQuality::High)
+        ));
+        assert!(!Connection::wants(
+            &low,
+            &frame,
+            &Some(text::VideoQuality::High)
+        ));
+        assert!(!Connection::wants(
+            &high,
+            &frame,
+            &Some(text::VideoQuality::Low)
+        ));
+        assert!(Connection::wants(
+            &low,
+            &frame,
+            &Some(text::VideoQuality::Low)
+        ));
+
+        // Frames intended for all the peers, such as passthrough ones when configured so
+        assert!(Connection::wants(&high, &frame, &None));
+        assert!(Connection::wants(&low, &frame, &None));
+    }
+
+    #[tokio::test]
+    async fn it_disconnects_the_peers_which_cannot_keep_up_with_the_metadata(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let _client = TcpStream::connect(listener.local_addr()?).await?;
+        let (stream, _) = listener.accept().await?;
+
+        let (notifier, notifications) = flume::unbounded();
+        let connection = Connection::spawn(
+            0,
+            Arc::new(RwLock::new(peer(text::VideoQuality::High))),
+            stream.into(),
+            &Config {
+                metadata_queue: 1,
+                ..Default::default()
+            },
+            notifier,
+        );
+
+        // The connection task does not run in between, so the queue cannot drain
+        connection.push(&metadata(0)?, &None).await;
+        connection.push(&metadata(1)?, &None).await;
+
+        let (_, notification) =
+            tokio::time::timeout(Duration::from_secs(10), notifications.recv_async()).await??;
+        let Notification::Closed(Some(err)) = notification else {
+            return Err("The connection was not closed with an error".into());
+        };
+
+        assert!(matches!(*err, Error::Overflow));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,432 @@
+use ffmpeg::{
+    codec,
+    software::{resampling, scaling},
+    Rescale,
+};
+
+use crate::{
+    io::frame::{audio, video, Frame},
+    Result,
+};
+
+use super::VideoCodec;
+
+/// A [`scaling::Context`] that can be moved between threads.
+struct Converter(scaling::Context);
+
+// SAFETY: The scaling context is exclusively owned by it's encoder and never shared,
+// and the underlying `SwsContext` holds no thread-local state.
+unsafe impl Send for Converter {}
+
+/// The stream parameters a [`VideoEncoder`] was opened with.
+#[derive(PartialEq)]
+struct VideoParams {
+    format: ffmpeg::format::Pixel,
+    width: u32,
+    height: u32,
+    framerate: ffmpeg::Rational,
+}
+
+struct VideoContext {
+    params: VideoParams,
+    fourcc: video::FourCCVideoType,
+    width: u32,
+    converter: Converter,
+    encoder: codec::encoder::Video,
+    extradata: Vec<u8>,
+    frames: i64,
+}
+
+/// A long-lived video encoder, only re-created when the stream parameters change.
+///
+/// With [`VideoCodec::SpeedHQ`], frames are sent as `SHQ2`, padded to a width multiple of 16 when needed,
+/// and frames with an alpha channel lose it since the `speedhq` encoder of `libavcodec` cannot produce `SHQ7`.
+#[derive(Default)]
+pub struct VideoEncoder {
+    codec: VideoCodec,
+    context: Option<VideoContext>,
+    max_width: Option<u32>,
+}
+
+impl VideoEncoder {
+    /// Create an encoder compressing the frames with the provided `codec`.
+    pub fn new(codec: VideoCodec) -> Self {
+        Self {
+            codec,
+            context: None,
+            max_width: None,
+        }
+    }
+
+    /// Create an encoder downscaling the frames wider than `max_width`, preserving their aspect ratio.
+    pub fn scaled(codec: VideoCodec, max_width: u32) -> Self {
+        Self {
+            codec,
+            context: None,
+            max_width: Some(max_width),
+        }
+    }
+
+    fn open(
+        params: VideoParams,
+        codec: VideoCodec,
+        max_width: Option<u32>,
+    ) -> Result<VideoContext> {
+        let (encoder, fourcc) = match codec {
+            VideoCodec::SpeedHQ => {
+                if video::FourCCVideoType::from_format(params.format)
+                    == video::FourCCVideoType::SHQ7
+                {
+                    tracing::warn!(
+                        "`{:?}` frames carry an alpha channel, which is dropped as SpeedHQ frames are sent as `SHQ2`",
+                        params.format
+                    );
+                }
+
+                let encoder = codec::encoder::find(codec::Id::SPEEDHQ)
+                    .ok_or(ffmpeg::Error::EncoderNotFound)?;
+
+                (encoder, video::FourCCVideoType::SHQ2)
+            }
+            VideoCodec::H264 => {
+                // Prefer `libx264` when linked, as the other encoders may be hardware ones.
+                let encoder = codec::encoder::find_by_name("libx264")
+                    .or_else(|| codec::encoder::find(codec::Id::H264))
+                    .ok_or(ffmpeg::Error::EncoderNotFound)?;
+
+                (encoder, video::FourCCVideoType::H264)
+            }
+        };
+
+        let (width, height) = match max_width {
+            Some(max_width) if params.width > max_width => (
+                max_width,
+                (params.height as u64 * max_width as u64 / params.width as u64) as u32 & !1,
+            ),
+            _ => (params.width, params.height),
+        };
+
+        let converter = Converter(scaling::Context::get(
+            params.format,
+            params.width,
+            params.height,
+            fourcc.to_format(),
+            width,
+            height,
+            scaling::Flags::FAST_BILINEAR,
+        )?);
+
+        let mut context = codec::Context::new().encoder().video()?;
+        context.set_format(fourcc.to_format());
+        context.set_width(fourcc.coded_width(width));
+        context.set_height(height);
+
+        let mut options = ffmpeg::Dictionary::new();
+        if fourcc.is_inter() {
+            context.set_time_base(params.framerate.invert());
+            context.set_frame_rate(Some(params.framerate));
+
+            // A keyframe every second so that new peers start quickly, and no B-frames
+            // so that frames are sent as soon as they are encoded.
+            context.set_gop((f64::from(params.framerate).ceil() as u32).max(1));
+            context.set_max_b_frames(0);
+            context.set_flags(codec::Flags::GLOBAL_HEADER);
+
+            options.set("preset", "veryfast");
+            options.set("tune", "zerolatency");
+        } else {
+            context.set_time_base(params.framerate);
+        }
+
+        let encoder = context.open_as_with(encoder, options)?;
+
+        // SAFETY: The encoder was successfully opened, and owns it's `extradata`
+        // buffer of `extradata_size` bytes when it is not null.
+        let extradata = unsafe {
+            let context = encoder.as_ptr();
+
+            if (*context).extradata.is_null() {
+                Vec::new()
+            } else {
+                std::slice::from_raw_parts((*context).extradata, (*context).extradata_size as usize)
+                    .to_vec()
+            }
+        };
+
+        tracing::debug!(
+            "Opened a new `{}` encoder for {}x{}@{}",
+            fourcc.as_ref(),
+            width,
+            height,
+            params.framerate
+        );
+
+        Ok(VideoContext {
+            params,
+            fourcc,
+            width: fourcc.coded_width(width),
+            converter,
+            encoder,
+            extradata,
+            frames: 0,
+        })
+    }
+
+    /// Encode a [`ffmpeg::frame::Video`] to the video [`Frame`]s in the wire format
+    /// produced by the encoder, which may be none while it buffers.
+    pub fn encode(
+        &mut self,
+        frame: &ffmpeg::frame::Video,
+        framerate: ffmpeg::Rational,
+    ) -> Result<Vec<Frame>> {
+        let params = VideoParams {
+            format: frame.format(),
+            width: frame.width(),
+            height: frame.height(),
+            framerate,
+        };
+
+        let context = match &mut self.context {
+            Some(context) if context.params == params => context,
+            context => context.insert(Self::open(params, self.codec, self.max_width)?),
+        };
+
+        let mut converted = ffmpeg::frame::Video::empty();
+        context.converter.0.run(frame, &mut converted)?;
+
+        let (width, height) = (converted.width(), converted.height());
+        if width < context.width {
+            converted = pad(&converted, context.width);
+        }
+
+        converted.set_pts(Some(context.frames));
+        context.frames += 1;
+
+        context.encoder.send_frame(&converted)?;
+
+        let spec = video::Spec {
+            fourcc: context.fourcc,
+            width,
+            height,
+            fps_num: framerate.numerator() as u32,
+            fps_den: framerate.denominator() as u32,
+            aspect_ratio: frame.width() as f32 / frame.height() as f32,
+            frame_format: video::FrameFormat::Progressive,
+            timestamp: chrono::Utc::now().into(),
+            ..Default::default()
+        };
+
+        // Drain every packet the encoder has ready, as a single frame may release several.
+        let mut frames = Vec::new();
+        loop {
+            let mut packet = ffmpeg::Packet::empty();
+            match context.encoder.receive_packet(&mut packet) {
+                Ok(()) => (),
+                Err(ffmpeg::Error::Other {
+                    errno: ffmpeg::error::EAGAIN,
+                }) => break Ok(frames),
+                Err(err) => break Err(err.into()),
+            }
+
+            frames.push(Frame::video(
+                spec.clone(),
+                Self::wrap(context, &packet, framerate)?,
+            ));
+        }
+    }
+
+    /// Wrap the `packet` data in a [`video::Packet`] for inter-frame codecs.
+    fn wrap(
+        context: &VideoContext,
+        packet: &ffmpeg::Packet,
+        framerate: ffmpeg::Rational,
+    ) -> Result<Vec<u8>> {
+        let data = packet.data().ok_or(ffmpeg::Error::InvalidData)?.to_vec();
+        if !context.fourcc.is_inter() {
+            return Ok(data);
+        }
+
+        let timebase = framerate.invert();
+        let keyframe = packet.is_key();
+
+        Ok(video::Packet {
+            fourcc: context.fourcc,
+            pts: packet
+                .pts()
+                .unwrap_or_default()
+                .rescale(timebase, video::TIMEBASE),
+            dts: packet
+                .dts()
+                .unwrap_or_default()
+                .rescale(timebase, video::TIMEBASE),
+            keyframe,
+            data,
+            extradata: if keyframe {
+                context.extradata.clone()
+            } else {
+                Vec::new()
+            },
+        }
+        .to_bytes())
+    }
+}
+
+/// Pad the planar 8-bit `frame` to `width`, repeating the last column of each plane,
+/// so that the picture is left unscaled and the decoder may crop the padding away.
+fn pad(frame: &ffmpeg::frame::Video, width: u32) -> ffmpeg::frame::Video {
+    let mut padded = ffmpeg::frame::Video::new(frame.format(), width, frame.height());
+
+    for plane in 0..frame.planes() {
+        let (from, to) = (
+            frame.plane_width(plane) as usize,
+            padded.plane_width(plane) as usize,
+        );
+        let (stride, padded_stride) = (frame.stride(plane), padded.stride(plane));
+
+        let source = frame.data(plane);
+        let target = padded.data_mut(plane);
+        for row in 0..frame.plane_height(plane) as usize {
+            let line = &source[row * stride..row * stride + from];
+            let out = &mut target[row * padded_stride..row * padded_stride + to];
+
+            out[..from].copy_from_slice(line);
+            out[from..].fill(line.last().copied().unwrap_or_default());
+        }
+    }
+
+    padded
+}
+
+/// The stream parameters an [`AudioEncoder`] was opened with.
+#[derive(PartialEq)]
+struct AudioParams {
+    format: ffmpeg::format::Sample,
+    layout: ffmpeg::ChannelLayout,
+    rate: u32,
+}
+
+struct AudioContext {
+    params: AudioParams,
+    fourcc: audio::FourCCAudioType,
+    resampler: resampling::Context,
+}
+
+/// A long-lived PCM encoder, only re-created when the stream parameters change.
+#[derive(Default)]
+pub struct AudioEncoder {
+    context: Option<AudioContext>,
+}
+
+impl AudioEncoder {
+    fn open(params: AudioParams) -> Result<AudioContext> {
+        let fourcc = audio::FourCCAudioType::from_format(params.format);
+        let resampler = resampling::Context::get(
+            params.format,
+            params.layout,
+            params.rate,
+            fourcc.to_format(),
+            params.layout,
+            params.rate,
+        )?;
+
+        tracing::debug!(
+            "Opened a new `{}` encoder for {} channels@{}Hz",
+            fourcc.as_ref(),
+            params.layout.channels(),
+            params.rate
+        );
+
+        Ok(AudioContext {
+            params,
+            fourcc,
+            resampler,
+        })
+    }
+
+    /// Encode a [`ffmpeg::frame::Audio`] to an audio [`Frame`] in the wire format.
+    pub fn encode(&mut self, frame: &ffmpeg::frame::Audio) -> Result<Frame> {
+        let params = AudioParams {
+            format: frame.format(),
+            layout: match frame.channel_layout() {
+                layout if layout.is_empty() => {
+                    ffmpeg::ChannelLayout::default(frame.channels().into())
+                }
+                layout => layout,
+            },
+            rate: frame.rate(),
+        };
+
+        let context = match &mut self.context {
+            Some(context) if context.params == params => context,
+            context => context.insert(Self::open(params)?),
+        };
+
+        let mut converted = ffmpeg::frame::Audio::empty();
+        context.resampler.run(frame, &mut converted)?;
+
+        let channels = context.params.layout.channels() as usize;
+
+        let len = audio::plane_len(&converted, channels);
+        let data = (0..converted.planes())
+            .flat_map(|plane| converted.data(plane)[..len].iter().copied())
+            .collect();
+
+        Ok(Frame::audio(
+            audio::Spec {
+                fourcc: context.fourcc,
+                samples: converted.samples() as u32,
+                num_channels: channels as u32,
+                sample_rate: converted.rate(),
+            },
+            data,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ffmpeg::format::Pixel;
+
+    use super::*;
+
+    #[test]
+    fn it_drops_the_alpha_channel_in_speedhq() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let frame = ffmpeg::frame::Video::new(Pixel::YUVA422P, 64, 32);
+        let Some(Frame::Video(block)) = VideoEncoder::new(VideoCodec::SpeedHQ)
+            .encode(&frame, ffmpeg::Rational::new(30, 1))?
+            .pop()
+        else {
+            return Err("No video frame was encoded".into());
+        };
+
+        assert_eq!(block.header.fourcc, video::FourCCVideoType::SHQ2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_pads_speedhq_frames_without_scaling_them() -> Result<(), Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+
+        let mut encoder = VideoEncoder::new(VideoCodec::SpeedHQ);
+
+        for width in [100, 8] {
+            let frame = ffmpeg::frame::Video::new(Pixel::YUV422P, width, 32);
+
+            let Some(Frame::Video(block)) =
+                encoder.encode(&frame, ffmpeg::Rational::new(30, 1))?.pop()
+            else {
+                return Err("No video frame was encoded".into());
+            };
+
+            assert_eq!(block.header.fourcc, video::FourCCVideoType::SHQ2);
+            assert_eq!(block.header.width, width);
+            assert_eq!(block.header.height, 32);
+            assert_eq!(block.header.aspect_ratio, width as f32 / 32.0);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use tokio::sync::{broadcast, watch};
+
+use crate::{io::frame::text, Error};
+
+use super::Peer;
+
+/// An event related to the peers of the `Source`.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A new peer connected and completed it's handshake.
+    Connected(Peer),
+
+    /// A peer disconnected from the `Source`.
+    Disconnected {
+        /// The peer, in it's last known state.
+        peer: Peer,
+
+        /// The error which terminated the connection, if any.
+        reason: Option<Arc<Error>>,
+    },
+
+    /// A peer changed it's requested _quality_ or _enabled streams_.
+    ///
+    /// The _tally_ changes of the peers are reported by `Source::subscribe_tally_changes` instead.
+    Updated(Peer),
+}
+
+/// A change of the _tally_ sent by one of the peers of the `Source`.
+#[derive(Debug, Clone)]
+pub struct TallyChange {
+    /// The peer which sent the new _tally_.
+    pub peer: Peer,
+
+    /// The _tally_ of the peer before the change.
+    pub previous: text::Tally,
+
+    /// The _tally_ of the peer after the change.
+    pub current: text::Tally,
+}
+
+/// The senders used by the listener to notify the subscribers of the `Source`.
+pub(super) struct Notifiers {
+    pub tally: watch::Sender<text::Tally>,
+    pub tally_changes: broadcast::Sender<TallyChange>,
+    pub events: broadcast::Sender<Event>,
+}
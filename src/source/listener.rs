@@ -0,0 +1,75 @@
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
+};
+
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::Result;
+
+/// A set of [`TcpListener`]s sharing the same port, one per listening address.
+pub struct Listener {
+    listeners: Vec<TcpListener>,
+    port: u16,
+}
+
+impl Listener {
+    /// Listen on all the `addresses`, using the first port of the `ports` range available on all of them.
+    pub async fn bind(addresses: &[IpAddr], ports: RangeInclusive<u16>) -> Result<Self> {
+        let mut error = None;
+
+        for port in ports {
+            match Self::bind_all(addresses, port).await {
+                Ok(listener) => return Ok(listener),
+                Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+                    tracing::debug!("Port `{port}` is not available for listening: {err}");
+
+                    error = Some(err);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Err(error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty port range"))
+            .into())
+    }
+
+    async fn bind_all(addresses: &[IpAddr], port: u16) -> io::Result<Self> {
+        let Some((first, others)) = addresses.split_first() else {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                "No address to listen on",
+            ));
+        };
+
+        // Bind the first address to know the port when it is picked by the system.
+        let first = TcpListener::bind((*first, port)).await?;
+        let port = first.local_addr()?.port();
+
+        let mut listeners = vec![first];
+        for address in others {
+            listeners.push(TcpListener::bind((*address, port)).await?);
+        }
+
+        Ok(Self { listeners, port })
+    }
+
+    /// The port shared by all the listeners.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Accept a new incoming connection from any of the listeners.
+    pub async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        let (accepted, _, _) = futures::future::select_all(
+            self.listeners
+                .iter()
+                .map(|listener| Box::pin(listener.accept())),
+        )
+        .await;
+
+        accepted
+    }
+}
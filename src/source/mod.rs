@@ -1,151 +1,317 @@
 //! Everything related to NDI [`Source`]s, to send video.
 
-use std::sync::{Arc, Weak};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Weak},
+    time::Duration,
+};
 
-use ffmpeg::codec;
-use futures::{StreamExt, TryFutureExt};
-use mdns_sd::{ServiceDaemon, ServiceInfo, UnregisterStatus};
-use slab::Slab;
-use tokio::{net::TcpListener, sync::RwLock};
+use futures::TryFutureExt;
+use mdns_sd::{IfKind, ServiceDaemon, ServiceInfo, UnregisterStatus};
+use tokio::{
+    sync::{broadcast, watch, Mutex, RwLock},
+    task::JoinHandle,
+    time::Instant,
+};
 
 use crate::{
+    discovery,
     io::{
-        frame::{text, video, Frame, FrameKind},
+        frame::{text, video, Frame},
         Stream,
     },
-    Error, Result,
+    Error, Result, SourceInfo,
 };
 
 mod config;
-pub use config::Config;
+pub use config::{Config, DropPolicy, VideoCodec};
 
 mod peer;
 pub use peer::Peer;
 
+pub(crate) mod encoder;
+use encoder::{AudioEncoder, VideoEncoder};
+
+mod listener;
+use listener::Listener;
+
+mod connection;
+use connection::{Connection, Notification};
+
+mod event;
+use event::Notifiers;
+pub use event::{Event, TallyChange};
+
 type Lock<T> = Arc<RwLock<T>>;
 type WeakLock<T> = Weak<RwLock<T>>;
 
+/// The maximum width of the proxy stream sent to peers requesting [`text::VideoQuality::Low`].
+const PROXY_WIDTH: u32 = 640;
+
+/// The capacity of the [`TallyChange`] and [`Event`] channels, before lagging subscribers miss some.
+const EVENTS: usize = 32;
+
+/// The delay before accepting connections again after a failure, such as running out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The delay after which the failed handshakes of a remote address are forgotten.
+const FAILURES_EXPIRY: Duration = Duration::from_secs(60 * 60);
+
+/// The maximum count of remote addresses whose failed handshakes are retained.
+const FAILURES: usize = 1024;
+
+/// The count of failed handshakes per remote address, along with the time of the last one.
+type Failures = HashMap<IpAddr, (usize, Instant)>;
+
+/// A [`Frame`] to be sent to the peers, with the video quality it is intended for, or `None` for all of them.
+type Outgoing = (Frame, Option<text::VideoQuality>);
+
 /// A _video_ and _audio_ source, that can send data to multiple sinks.
 pub struct Source {
-    name: String,
-    mdns: ServiceDaemon,
+    advertisement: Advertisement,
 
     peers: Lock<Vec<WeakLock<Peer>>>,
-    frames: flume::Sender<Frame>,
+    failures: Lock<Failures>,
+    frames: flume::Sender<Outgoing>,
+
+    tally: watch::Receiver<text::Tally>,
+    tally_changes: broadcast::Sender<TallyChange>,
+    events: broadcast::Sender<Event>,
+
+    video: Mutex<VideoEncoder>,
+    proxy: Mutex<VideoEncoder>,
+    audio: Mutex<AudioEncoder>,
+    passthrough_quality: Option<text::VideoQuality>,
 }
 
 impl Source {
     /// Expose a new [`Source`] based on the provided `config` on the network.
     pub async fn new(config: Config) -> Result<Self> {
         let groups = config.groups.as_deref().unwrap_or(&["public"]).join(",");
-        let listener = TcpListener::bind("[::]:0").await?;
+        let addresses = config.addresses()?;
+        let listener = Listener::bind(
+            addresses
+                .as_deref()
+                .unwrap_or(&[config.bind.unwrap_or(Ipv6Addr::UNSPECIFIED.into())]),
+            config.ports.clone().unwrap_or(0..=0),
+        )
+        .await?;
+
+        let advertisement = match config.discovery {
+            Some(server) => Advertisement::Discovery(tokio::spawn(discovery::client::register(
+                server,
+                SourceInfo {
+                    machine: crate::machine(),
+                    name: config.name.clone(),
+                    groups: SourceInfo::parse_groups(&groups),
+                    addresses: addresses.clone().unwrap_or_default(),
+                    port: listener.port(),
+                },
+            ))),
+            None => {
+                let mdns = ServiceDaemon::new()?;
+                let service = ServiceInfo::new(
+                    super::SERVICE_TYPE,
+                    &crate::name(&config.name),
+                    &crate::hostname(),
+                    addresses.as_deref().unwrap_or_default(),
+                    listener.port(),
+                    [("groups", groups.as_str())].as_slice(),
+                )?;
+
+                // Restrict the advertisement to the interfaces we listen on, if any.
+                let service = match (&addresses, &config.interfaces) {
+                    (None, _) => service.enable_addr_auto(),
+                    (Some(_), Some(interfaces)) => {
+                        mdns.disable_interface(IfKind::All)?;
+                        mdns.enable_interface(
+                            interfaces
+                                .iter()
+                                .cloned()
+                                .map(IfKind::Name)
+                                .collect::<Vec<_>>(),
+                        )?;
+
+                        service
+                    }
+                    (Some(addresses), None) => {
+                        mdns.disable_interface(IfKind::All)?;
+                        mdns.enable_interface(
+                            addresses
+                                .iter()
+                                .copied()
+                                .map(IfKind::Addr)
+                                .collect::<Vec<_>>(),
+                        )?;
+
+                        service
+                    }
+                };
 
-        let mdns = ServiceDaemon::new()?;
-        let service = ServiceInfo::new(
-            super::SERVICE_TYPE,
-            &crate::name(&config.name),
-            &crate::hostname(),
-            (),
-            listener.local_addr()?.port(),
-            [("groups", groups.as_str())].as_slice(),
-        )?
-        .enable_addr_auto();
+                let fullname = service.get_fullname().into();
+                mdns.register(service)?;
 
-        let name = service.get_fullname().into();
-        mdns.register(service)?;
+                tracing::debug!("Registered mDNS service `{}`", fullname);
 
-        tracing::debug!("Registered mDNS service `{}`", name);
+                Advertisement::Mdns { mdns, fullname }
+            }
+        };
 
         let peers = <Lock<Vec<WeakLock<Peer>>>>::default();
+        let failures = <Lock<Failures>>::default();
         let (frames, framesrx) = flume::bounded(1);
+        let (tallytx, tally) = watch::channel(Default::default());
+        let (tally_changes, _) = broadcast::channel(EVENTS);
+        let (events, _) = broadcast::channel(EVENTS);
+        let codec = config.video_codec;
+        let passthrough_quality = config.passthrough_quality.clone();
 
         tokio::spawn(
-            Self::listen(listener, config, peers.clone(), framesrx)
-                .inspect_err(|err| tracing::error!("Fatal error in `Source::listener`: {err}")),
+            Self::listen(
+                listener,
+                config,
+                peers.clone(),
+                failures.clone(),
+                framesrx,
+                Notifiers {
+                    tally: tallytx,
+                    tally_changes: tally_changes.clone(),
+                    events: events.clone(),
+                },
+            )
+            .inspect_err(|err| tracing::error!("Fatal error in `Source::listener`: {err}")),
         );
 
         Ok(Self {
-            name,
-            mdns,
+            advertisement,
             peers,
+            failures,
             frames,
+            tally,
+            tally_changes,
+            events,
+            video: Mutex::new(VideoEncoder::new(codec)),
+            proxy: Mutex::new(VideoEncoder::scaled(codec, PROXY_WIDTH)),
+            audio: Default::default(),
+            passthrough_quality,
         })
     }
 
     async fn listen(
-        listener: tokio::net::TcpListener,
+        listener: Listener,
         config: Config,
         peers: Lock<Vec<WeakLock<Peer>>>,
-        frames: flume::Receiver<Frame>,
+        failures: Lock<Failures>,
+        frames: flume::Receiver<Outgoing>,
+        notifiers: Notifiers,
     ) -> Result {
-        let mut streams: Slab<(Lock<Peer>, Stream)> = Slab::with_capacity(32);
+        // Connections are keyed by a monotonic identifier, so that the notifications
+        // of a closed connection can never be attributed to a newer one.
+        let mut connections: HashMap<u64, Connection> = HashMap::with_capacity(32);
+        let mut next = 0u64;
+        let (handshakes, handshaken) = flume::unbounded();
+        let (notifier, notifications) = flume::unbounded();
+        let mut backoff = std::pin::pin!(tokio::time::sleep(Duration::ZERO));
 
         loop {
             tokio::select! {
-                // Accept new connections in the pool
-                accepted = listener.accept() => {
-                    let (stream, _) = accepted?;
-                    let mut stream = stream.into();
-
-                    let peer = tokio::time::timeout(
-                        crate::HANDSHAKE_TIMEOUT,
-                        Peer::handshake(&mut stream, &config)
-                    )
-                    .await??;
+                // Resume accepting connections once the backoff elapsed
+                () = &mut backoff, if !backoff.is_elapsed() => {}
+
+                // Accept new connections and handshake them concurrently
+                accepted = listener.accept(), if backoff.is_elapsed() => {
+                    let (stream, addr) = match accepted {
+                        Ok(accepted) => accepted,
+                        Err(err) => {
+                            tracing::warn!("Unable to accept an incoming connection: {err}");
+
+                            // Avoid spinning on persistent failures, such as `EMFILE`,
+                            // while still delivering the frames to the connected peers.
+                            backoff.as_mut().reset(Instant::now() + ACCEPT_BACKOFF);
+
+                            continue;
+                        }
+                    };
+
+                    tokio::spawn(Self::handshake(
+                        stream.into(),
+                        addr,
+                        config.clone(),
+                        failures.clone(),
+                        handshakes.clone(),
+                    ));
+                }
+
+                // Add successfully handshaked peers to the pool
+                Ok((peer, stream)) = handshaken.recv_async() => {
                     let peer = Arc::from(RwLock::new(peer));
 
                     peers.write().await.push(Arc::downgrade(&peer));
-                    streams.insert((peer, stream));
-                }
 
-                // Receive metadata from peers
-                Some(mut entry) = async {
-                    let mut readable = streams
-                        .iter_mut()
-                        .map(|(idx, entry)| async move { entry.1.readable().await.ok(); (idx, entry) })
-                        .collect::<futures::stream::FuturesUnordered<_>>();
+                    let id = next;
+                    next += 1;
 
-                    readable.next().await
-                } => {
-                    let (idx, (peer, stream)) = &mut entry;
+                    let connection = connections.entry(id).or_insert(Connection::spawn(
+                        id,
+                        peer,
+                        stream,
+                        &config,
+                        notifier.clone(),
+                    ));
 
-                    match stream.metadata().await {
-                        Ok(Some(text::Metadata::Tally(tally))) => {
-                            peer.write().await.tally = tally;
-                        }
-                        Ok(other) => tracing::debug!("Ignored metadata from peer: {other:?}"),
-                        Err(err) => {
-                            tracing::error!("Peer handling failed: {err}");
+                    notifiers.events.send(Event::Connected(connection.peer().await)).ok();
+
+                    Self::aggregate(&connections, &notifiers.tally).await;
+                }
 
-                            streams.remove(*idx);
+                // Track the changes reported by the connections
+                Ok((id, notification)) = notifications.recv_async() => {
+                    match notification {
+                        Notification::Tally { previous, current } => {
+                            if let Some(connection) = connections.get(&id) {
+                                notifiers
+                                    .tally_changes
+                                    .send(TallyChange {
+                                        peer: connection.peer().await,
+                                        previous,
+                                        current,
+                                    })
+                                    .ok();
+                            }
+                        }
+                        Notification::Updated => {
+                            if let Some(connection) = connections.get(&id) {
+                                notifiers.events.send(Event::Updated(connection.peer().await)).ok();
+                            }
+                        }
+                        Notification::Closed(reason) => {
+                            if let Some(connection) = connections.remove(&id) {
+                                notifiers
+                                    .events
+                                    .send(Event::Disconnected {
+                                        peer: connection.peer().await,
+                                        reason,
+                                    })
+                                    .ok();
+                            }
                         }
                     }
+
+                    Self::aggregate(&connections, &notifiers.tally).await;
                 }
 
-                // Send frames to all peers
-                Ok(frame) = frames.recv_async() => {
+                // Queue frames to all peers, until the `Source` is dropped
+                outgoing = frames.recv_async() => {
+                    let Ok((frame, quality)) = outgoing else {
+                        break Ok(());
+                    };
+
+                    let frame = Arc::new(frame);
                     futures::future::join_all(
-                        streams
-                            .iter_mut()
-                            .map(|(_, entry)| {
-                                let frame = &frame;
-
-                                async move {
-                                    let (peer, stream) = entry;
-                                    let peer = peer.read().await;
-
-                                    if (peer.streams.text && matches!(frame, Frame::Text { .. }))
-                                        || (peer.streams.video && matches!(frame, Frame::Video { .. }))
-                                        || (peer.streams.audio && matches!(frame, Frame::Audio { .. })) {
-                                        tracing::trace!("-> sending {:?} frame to `{}`", frame, peer.identify.name);
-
-                                        drop(peer);
-                                        stream.send(frame).await.ok();
-                                    } else {
-                                        tracing::trace!("-x-> skip sending {:?} frame to `{}`", FrameKind::from(frame), peer.identify.name);
-                                    }
-                                }
-                            })
+                        connections
+                            .values()
+                            .map(|connection| connection.push(&frame, &quality))
                     )
                     .await;
                 }
@@ -153,6 +319,67 @@ impl Source {
         }
     }
 
+    /// Compute the _tally_ of all the `connections`, and notify the subscribers if it changed.
+    async fn aggregate(connections: &HashMap<u64, Connection>, tally: &watch::Sender<text::Tally>) {
+        let mut aggregated = text::Tally::default();
+        for connection in connections.values() {
+            aggregated = aggregated | connection.peer().await.tally;
+        }
+
+        tally.send_if_modified(|current| {
+            let modified = *current != aggregated;
+            *current = aggregated;
+
+            modified
+        });
+    }
+
+    async fn handshake(
+        mut stream: Stream,
+        addr: SocketAddr,
+        config: Config,
+        failures: Lock<Failures>,
+        handshakes: flume::Sender<(Peer, Stream)>,
+    ) {
+        let handshake = async {
+            Ok::<_, Error>(
+                tokio::time::timeout(
+                    crate::HANDSHAKE_TIMEOUT,
+                    Peer::handshake(&mut stream, &config),
+                )
+                .await??,
+            )
+        };
+
+        match handshake.await {
+            Ok(peer) => {
+                handshakes.send_async((peer, stream)).await.ok();
+            }
+            Err(err) => {
+                let mut failures = failures.write().await;
+                let now = Instant::now();
+
+                failures.retain(|_, (_, last)| now.duration_since(*last) < FAILURES_EXPIRY);
+                if failures.len() >= FAILURES && !failures.contains_key(&addr.ip()) {
+                    // Forget the address whose last failure is the oldest to make room
+                    if let Some(oldest) = failures
+                        .iter()
+                        .min_by_key(|(_, (_, last))| *last)
+                        .map(|(ip, _)| *ip)
+                    {
+                        failures.remove(&oldest);
+                    }
+                }
+
+                let (count, last) = failures.entry(addr.ip()).or_insert((0, now));
+                *count += 1;
+                *last = now;
+
+                tracing::warn!("Handshake with `{addr}` failed ({count} time(s) so far): {err}");
+            }
+        }
+    }
+
     /// List the peers currently connected to the [`Source`], with their parameters.
     pub async fn peers(&self) -> Vec<Peer> {
         let pointers: Vec<_> = self
@@ -175,64 +402,161 @@ impl Source {
         peers
     }
 
-    /// Get current _tally_ information computed from all the connected peers of the [`Source`].
-    pub async fn tally(&self) -> text::Tally {
-        self.peers()
+    /// Count the failed handshakes per remote address, forgetting the addresses which
+    /// did not fail for an hour, and retaining up to the `1024` most recent ones.
+    pub async fn failures(&self) -> HashMap<IpAddr, usize> {
+        let now = Instant::now();
+
+        self.failures
+            .read()
+            .await
+            .iter()
+            .filter(|(_, (_, last))| now.duration_since(*last) < FAILURES_EXPIRY)
+            .map(|(ip, (count, _))| (*ip, *count))
+            .collect()
+    }
+
+    /// Whether any of the connected peers requested the provided video `quality`.
+    async fn wants(&self, quality: &text::VideoQuality) -> bool {
+        let pointers: Vec<_> = self
+            .peers
+            .read()
             .await
-            .into_iter()
-            .fold(Default::default(), |current, peer| current | peer.tally)
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+
+        for peer in pointers {
+            if peer.read().await.quality == *quality {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Get current _tally_ information computed from all the connected peers of the [`Source`].
+    pub fn tally(&self) -> text::Tally {
+        self.tally.borrow().clone()
+    }
+
+    /// Subscribe to the _tally_ information computed from all the connected peers of the [`Source`],
+    /// the receiver is notified whenever the _program_ or _preview_ state changes.
+    pub fn subscribe_tally(&self) -> watch::Receiver<text::Tally> {
+        self.tally.clone()
+    }
+
+    /// Subscribe to the _tally_ changes of the individual peers of the [`Source`],
+    /// to know which one of them put it _on program_ or _on preview_.
+    ///
+    /// Subscribers which do not keep up with the changes miss the oldest ones,
+    /// see [`broadcast::error::RecvError::Lagged`].
+    pub fn subscribe_tally_changes(&self) -> broadcast::Receiver<TallyChange> {
+        self.tally_changes.subscribe()
+    }
+
+    /// Stream the [`Event`]s of the peers of the [`Source`], as they connect, change or disconnect.
+    ///
+    /// Events are only emitted from the moment this method is called, and the oldest ones
+    /// are skipped if the stream is not polled fast enough.
+    pub fn events(&self) -> impl futures::Stream<Item = Event> {
+        futures::stream::unfold(self.events.subscribe(), |mut events| async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => break Some((event, events)),
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        tracing::warn!(
+                            "Skipped {count} peer event(s), as the stream lagged behind"
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break None,
+                }
+            }
+        })
     }
 
     /// Broadcast a [`ffmpeg::frame::Video`] to all the connected peers.
+    ///
+    /// With [`VideoCodec::SpeedHQ`], frames are padded to a width multiple of 16 when needed, which
+    /// the sinks crop away, and frames in a pixel format carrying an alpha channel (`RGBA`, `BGRA`,
+    /// `YUVA`, ..) are sent without it, as the SpeedHQ encoder of `libavcodec` cannot produce `SHQ7`.
+    ///
+    /// With [`VideoCodec::H264`], frames are sent as `H264` without their alpha channel,
+    /// and the encoder may hold back the first ones before sending anything.
     pub async fn broadcast_video(
         &self,
         frame: &ffmpeg::frame::Video,
         framerate: ffmpeg::Rational,
     ) -> Result {
-        assert!(
-            frame.width() % 16 == 0,
-            "SpeedHQ frame width must be a multiple of 16, was `{}`",
-            frame.width()
-        );
-
-        let mut converted = ffmpeg::frame::Video::new(
-            ffmpeg::format::Pixel::YUV422P,
-            frame.width(),
-            frame.height(),
-        );
+        // Frames no wider than the proxy stream are sent as-is to the peers requesting it.
+        let downscale = frame.width() > PROXY_WIDTH;
+        let quality = downscale.then_some(text::VideoQuality::High);
+
+        let encoded = self.video.lock().await.encode(frame, framerate)?;
+        for encoded in encoded {
+            self.frames
+                .send_async((encoded, quality.clone()))
+                .await
+                .map_err(|_| Error::ClosedChannel)?;
+        }
 
-        frame
-            .converter(converted.format())?
-            .run(frame, &mut converted)?;
+        // Only spend the proxy encode when at least one peer is able to receive it.
+        if downscale && self.wants(&text::VideoQuality::Low).await {
+            let encoded = self.proxy.lock().await.encode(frame, framerate)?;
+            for encoded in encoded {
+                self.frames
+                    .send_async((encoded, Some(text::VideoQuality::Low)))
+                    .await
+                    .map_err(|_| Error::ClosedChannel)?;
+            }
+        }
 
-        let mut context = codec::Context::new().encoder().video()?;
-        context.set_time_base(framerate);
-        context.set_format(converted.format());
-        context.set_width(converted.width());
-        context.set_height(converted.height());
+        Ok(())
+    }
 
-        let mut encoder = context.open_as(codec::encoder::find(codec::Id::SPEEDHQ))?;
-        encoder.send_frame(&converted)?;
-        encoder.send_eof()?;
+    /// Broadcast a pre-compressed video `packet` described by `spec` to the connected peers as-is,
+    /// without any conversion or encoding.
+    ///
+    /// The timestamps of `H264` and `HEVC` packets are expected in [`crate::video::TIMEBASE`], see
+    /// [`ffmpeg::Packet::rescale_ts`], and their parameter sets either in-band or in the
+    /// [`ffmpeg::codec::packet::side_data::Type::NewExtraData`] side data of keyframes.
+    ///
+    /// As no proxy stream can be produced without decoding, the packet is only sent to the peers
+    /// requesting the [`Config::passthrough_quality`], by default not to the ones requesting
+    /// [`text::VideoQuality::Low`].
+    pub async fn broadcast_video_packet(
+        &self,
+        packet: &ffmpeg::Packet,
+        mut spec: video::Spec,
+    ) -> Result {
+        let data = packet.data().ok_or(ffmpeg::Error::InvalidData)?.to_vec();
+
+        let data = if spec.fourcc.is_inter() {
+            let extradata = packet
+                .side_data()
+                .find(|side| side.kind() == ffmpeg::codec::packet::side_data::Type::NewExtraData)
+                .map(|side| side.data().to_vec())
+                .unwrap_or_default();
+
+            video::Packet {
+                fourcc: spec.fourcc,
+                pts: packet.pts().unwrap_or_default(),
+                dts: packet.dts().or(packet.pts()).unwrap_or_default(),
+                keyframe: packet.is_key(),
+                data,
+                extradata,
+            }
+            .to_bytes()
+        } else {
+            data
+        };
 
-        let mut packet = ffmpeg::Packet::empty();
-        encoder.receive_packet(&mut packet)?;
+        if spec.aspect_ratio == 0.0 && spec.height != 0 {
+            spec.aspect_ratio = spec.width as f32 / spec.height as f32;
+        }
 
         self.frames
-            .send_async(Frame::video(
-                video::Spec {
-                    fourcc: video::FourCCVideoType::SHQ2,
-                    width: converted.width(),
-                    height: converted.height(),
-                    fps_num: framerate.numerator() as u32,
-                    fps_den: framerate.denominator() as u32,
-                    aspect_ratio: converted.width() as f32 / converted.height() as f32,
-                    frame_format: video::FrameFormat::Progressive,
-                    timestamp: chrono::Utc::now().into(),
-                    ..Default::default()
-                },
-                packet.data().expect("No packet data ??").to_vec(),
-            ))
+            .send_async((Frame::video(spec, data), self.passthrough_quality.clone()))
             .await
             .map_err(|_| Error::ClosedChannel)?;
 
@@ -240,31 +564,73 @@ impl Source {
     }
 
     /// Broadcast a [`ffmpeg::frame::Audio`] to all the connected peers.
-    pub fn broadcast_audio(&self, frame: &ffmpeg::frame::Audio) -> Result {
-        todo!("Broadcast an audio frame")
+    pub async fn broadcast_audio(&self, frame: &ffmpeg::frame::Audio) -> Result {
+        let frame = self.audio.lock().await.encode(frame)?;
+
+        self.frames
+            .send_async((frame, None))
+            .await
+            .map_err(|_| Error::ClosedChannel)?;
+
+        Ok(())
     }
+
+    /// Broadcast arbitrary `xml` metadata to all the connected peers.
+    ///
+    /// The `xml` must be well-formed, or [`Error::Xml`] is returned without sending anything.
+    pub async fn broadcast_metadata(&self, xml: &str) -> Result {
+        let frame = Frame::metadata(xml)?;
+
+        self.frames
+            .send_async((frame, None))
+            .await
+            .map_err(|_| Error::ClosedChannel)?;
+
+        Ok(())
+    }
+}
+
+/// How a [`Source`] is advertised on the network.
+enum Advertisement {
+    /// Advertised over mDNS, with the `fullname` of the registered service.
+    Mdns {
+        mdns: ServiceDaemon,
+        fullname: String,
+    },
+
+    /// Registered to a discovery server by the task.
+    Discovery(JoinHandle<()>),
 }
 
-impl Drop for Source {
+impl Drop for Advertisement {
     fn drop(&mut self) {
-        match self.mdns.unregister(&self.name).map(|recv| recv.recv()) {
+        match self {
+            Self::Mdns { mdns, fullname } => Self::unregister(mdns, fullname),
+            Self::Discovery(task) => task.abort(),
+        }
+    }
+}
+
+impl Advertisement {
+    fn unregister(mdns: &ServiceDaemon, fullname: &str) {
+        match mdns.unregister(fullname).map(|recv| recv.recv()) {
             Err(err) => tracing::error!(
                 "Error while unregistering service `{}` from mDNS: {err}",
-                self.name
+                fullname
             ),
             Ok(Err(err)) => tracing::error!(
                 "Error while unregistering service `{}` from mDNS: {err}",
-                self.name
+                fullname
             ),
             Ok(Ok(err @ UnregisterStatus::NotFound)) => tracing::error!(
                 "Error while unregistering service `{}` from mDNS: {err:?}",
-                self.name
+                fullname
             ),
 
-            _ => tracing::debug!("Unregistered mDNS service `{}`", self.name),
+            _ => tracing::debug!("Unregistered mDNS service `{}`", fullname),
         }
 
-        if let Err(err) = self.mdns.shutdown() {
+        if let Err(err) = mdns.shutdown() {
             tracing::error!("Error while shutting down the mDNS advertisement thread: {err}");
         }
     }
@@ -30,6 +30,9 @@ pub struct Peer {
 
     /// The _tally_ of the peer.
     pub tally: text::Tally,
+
+    /// The count of video frames dropped because the peer could not keep up with the stream.
+    pub dropped_video: u64,
 }
 
 impl Peer {
@@ -67,6 +70,7 @@ impl Peer {
                     streams: streams.take().unwrap(),
                     quality,
                     tally,
+                    dropped_video: 0,
                 };
 
                 tracing::debug!(
@@ -0,0 +1,130 @@
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use nndi::{discovery, scan, source, Scan, Sink, Source};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn source(
+    server: &discovery::Server,
+    name: &str,
+    groups: Option<Vec<&'static str>>,
+) -> nndi::Result<Source> {
+    Source::new(source::Config {
+        name: name.into(),
+        groups,
+        discovery: Some(server.local_addr()),
+        ..Default::default()
+    })
+    .await
+}
+
+fn scan(server: &discovery::Server, groups: Option<Vec<String>>) -> nndi::Result<Scan> {
+    Scan::new(scan::Config {
+        groups,
+        discovery: Some(server.local_addr()),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn it_discovers_and_connects_through_a_server() -> Result<(), Box<dyn std::error::Error>> {
+    let server = discovery::Server::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+
+    let _source = source(&server, "discovered source", None).await?;
+    let info = scan(&server, None)?
+        .wait_for("discovered source", TIMEOUT)
+        .await?;
+
+    assert_eq!(info.name, "discovered source");
+    assert_eq!(info.groups, ["public"]);
+    assert_eq!(info.addresses, [IpAddr::from(Ipv4Addr::LOCALHOST)]);
+
+    let sink = Sink::new(&info, Default::default()).await?;
+
+    assert_eq!(sink.peer().identify.name, info.to_string());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn it_removes_sources_disconnecting_from_the_server() -> Result<(), Box<dyn std::error::Error>>
+{
+    let server = discovery::Server::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+
+    let source = source(&server, "ephemeral source", None).await?;
+    let mut scan = scan(&server, None)?;
+    let info = scan.wait_for("ephemeral source", TIMEOUT).await?;
+
+    drop(source);
+
+    let event = tokio::time::timeout(TIMEOUT, std::pin::pin!(scan.events()).next()).await?;
+    assert!(matches!(event, Some(scan::Event::Removed(removed)) if removed == info));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn it_only_reports_sources_from_the_watched_groups() -> Result<(), Box<dyn std::error::Error>>
+{
+    let server = discovery::Server::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+
+    let _source = source(&server, "studio source", Some(vec!["studio-a"])).await?;
+
+    let result = scan(&server, Some(vec!["studio-b".into()]))?
+        .wait_for("studio source", Duration::from_millis(500))
+        .await;
+    assert!(matches!(result, Err(nndi::Error::Timeout(_))));
+
+    let info = scan(&server, Some(vec!["Studio-A".into()]))?
+        .wait_for("studio source", TIMEOUT)
+        .await?;
+    assert_eq!(info.groups, ["studio-a"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn it_reports_the_extra_sources_while_they_are_reachable(
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Neither the source nor the scan use mDNS, and they are not registered to the same server
+    let registry = discovery::Server::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let empty = discovery::Server::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+
+    let port = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+        .local_addr()?
+        .port();
+    let source = Source::new(source::Config {
+        name: "probed source".into(),
+        groups: Some(vec!["probed"]),
+        discovery: Some(registry.local_addr()),
+        bind: Some(Ipv4Addr::LOCALHOST.into()),
+        ports: Some(port..=port),
+        ..Default::default()
+    })
+    .await?;
+
+    // The extra sources are reported whatever the watched groups, as theirs are unknown
+    let mut scan = Scan::new(scan::Config {
+        groups: Some(vec!["elsewhere".into()]),
+        discovery: Some(empty.local_addr()),
+        extra_sources: vec![(Ipv4Addr::LOCALHOST, port).into()],
+        ..Default::default()
+    })?;
+    let info = scan.wait_for("probed source", TIMEOUT).await?;
+
+    assert_eq!(info.addresses, [IpAddr::from(Ipv4Addr::LOCALHOST)]);
+    assert_eq!(info.port, port);
+
+    drop(source);
+
+    let event = tokio::time::timeout(TIMEOUT, std::pin::pin!(scan.events()).next()).await?;
+    assert!(
+        matches!(event, Some(scan::Event::Removed(removed)) if removed.to_string() == info.to_string())
+    );
+
+    Ok(())
+}